@@ -9,6 +9,18 @@ pub struct Cli {
 
     #[facet(named, short = 'c', default = PathBuf::from("config.yaml"))]
     pub config: PathBuf,
+
+    /// Overrides `search.expression` from the config file.
+    #[facet(named, short = 'e', default)]
+    pub expression: Option<String>,
+
+    /// Continues the search saved in `runtime.checkpoint.path`.
+    #[facet(named, default)]
+    pub resume: bool,
+
+    /// Overwrites keys that already exist in `output.save-to`.
+    #[facet(named, default)]
+    pub force: bool,
 }
 
 impl Cli {
@@ -22,6 +34,7 @@ impl Cli {
 pub enum Command {
     Generate,
     Benchmark,
+    Estimate,
 }
 
 impl FromStr for Command {
@@ -31,6 +44,7 @@ impl FromStr for Command {
         match s.to_ascii_lowercase().as_str() {
             "generate" => Ok(Self::Generate),
             "benchmark" => Ok(Self::Benchmark),
+            "estimate" => Ok(Self::Estimate),
             _ => Err(format!("invalid command: {}", s)),
         }
     }
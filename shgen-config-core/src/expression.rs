@@ -0,0 +1,397 @@
+//! Boolean expressions over `keyword in field` terms, such as
+//! `(cat or dog) in public-key and not ugly in sha256`.
+//!
+//! `in` binds tighter than `not`, which binds tighter than `and`, which binds
+//! tighter than `or`. A parenthesized group followed by `in` applies the field
+//! to every keyword inside it. Keywords that collide with an operator, or that
+//! contain spaces or parentheses, can be quoted with `'` or `"`.
+
+use std::{fmt, str::FromStr};
+
+use crate::{keyword::Keyword, search::SearchFields};
+
+/// How deeply parentheses and `not` may nest, so that parsing and evaluating
+/// an expression can't overflow the stack.
+pub const MAX_DEPTH: usize = 64;
+
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(try_from = "String")
+)]
+pub enum Expression {
+    /// The keyword appears in the field.
+    Term {
+        keyword: Keyword,
+        field: SearchFields,
+    },
+    And(Vec<Expression>),
+    Or(Vec<Expression>),
+    Not(Box<Expression>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExpressionError {
+    #[error("Expected {expected} at position {position}, found {found:?}")]
+    Unexpected {
+        expected: &'static str,
+        found: String,
+        position: usize,
+    },
+    #[error("Expected {expected} at the end of the expression")]
+    UnexpectedEnd { expected: &'static str },
+    #[error("Quote at position {position} is never closed")]
+    UnclosedQuote { position: usize },
+    #[error("Unknown field {name:?} at position {position}")]
+    UnknownField { name: String, position: usize },
+    #[error("Keyword {keyword:?} needs a field, like `{keyword} in public-key`")]
+    MissingField { keyword: String },
+    #[error("Keywords before `in` at position {position} already have a field")]
+    FieldAlreadySet { position: usize },
+    #[error("Expression nests deeper than {MAX_DEPTH} levels at position {position}")]
+    TooDeep { position: usize },
+}
+
+impl Expression {
+    /// Every `keyword in field` term of the expression, in order.
+    #[must_use]
+    pub fn terms(&self) -> Vec<(&Keyword, &SearchFields)> {
+        let mut terms = Vec::new();
+        self.collect_terms(&mut terms);
+        terms
+    }
+
+    fn collect_terms<'a>(&'a self, terms: &mut Vec<(&'a Keyword, &'a SearchFields)>) {
+        match self {
+            Self::Term { keyword, field } => terms.push((keyword, field)),
+            Self::And(operands) | Self::Or(operands) => {
+                for operand in operands {
+                    operand.collect_terms(terms);
+                }
+            }
+            Self::Not(operand) => operand.collect_terms(terms),
+        }
+    }
+}
+
+impl FromStr for Expression {
+    type Err = ExpressionError;
+
+    fn from_str(source: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            tokens: tokenize(source)?,
+            position: 0,
+            depth: 0,
+        };
+
+        let node = parser.parse_or()?;
+        if let Some(token) = parser.tokens.get(parser.position) {
+            return Err(token.unexpected("`and`, `or` or the end"));
+        }
+
+        node.into_expression()
+    }
+}
+
+impl TryFrom<String> for Expression {
+    type Error = ExpressionError;
+
+    fn try_from(source: String) -> Result<Self, Self::Error> {
+        source.parse()
+    }
+}
+
+impl fmt::Display for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let join = |f: &mut fmt::Formatter<'_>, operands: &[Self], operator: &str| {
+            write!(f, "(")?;
+            for (index, operand) in operands.iter().enumerate() {
+                if index > 0 {
+                    write!(f, " {operator} ")?;
+                }
+                write!(f, "{operand}")?;
+            }
+            write!(f, ")")
+        };
+
+        match self {
+            Self::Term { keyword, field } => write!(f, "{:?} in {field}", keyword.text),
+            Self::And(operands) => join(f, operands, "and"),
+            Self::Or(operands) => join(f, operands, "or"),
+            Self::Not(operand) => write!(f, "not {operand}"),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum TokenKind {
+    Open,
+    Close,
+    Word(String),
+    Quoted(String),
+}
+
+#[derive(Debug)]
+struct Token {
+    kind: TokenKind,
+    position: usize,
+}
+
+impl Token {
+    fn is_operator(&self, operator: &str) -> bool {
+        matches!(&self.kind, TokenKind::Word(word) if word.eq_ignore_ascii_case(operator))
+    }
+
+    fn unexpected(&self, expected: &'static str) -> ExpressionError {
+        let found = match &self.kind {
+            TokenKind::Open => String::from("("),
+            TokenKind::Close => String::from(")"),
+            TokenKind::Word(text) | TokenKind::Quoted(text) => text.clone(),
+        };
+
+        ExpressionError::Unexpected {
+            expected,
+            found,
+            position: self.position,
+        }
+    }
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, ExpressionError> {
+    let mut tokens = Vec::new();
+    let mut characters = source.char_indices().peekable();
+
+    while let Some((position, character)) = characters.next() {
+        let kind = match character {
+            _ if character.is_whitespace() => continue,
+            '(' => TokenKind::Open,
+            ')' => TokenKind::Close,
+            '\'' | '"' => {
+                let mut text = String::new();
+                loop {
+                    match characters.next() {
+                        Some((_, closing)) if closing == character => break,
+                        Some((_, inner)) => text.push(inner),
+                        None => return Err(ExpressionError::UnclosedQuote { position }),
+                    }
+                }
+                TokenKind::Quoted(text)
+            }
+            _ => {
+                let mut text = String::from(character);
+                while let Some(&(_, next)) = characters.peek() {
+                    if next.is_whitespace() || matches!(next, '(' | ')' | '\'' | '"') {
+                        break;
+                    }
+                    text.push(next);
+                    characters.next();
+                }
+                TokenKind::Word(text)
+            }
+        };
+
+        tokens.push(Token { kind, position });
+    }
+
+    Ok(tokens)
+}
+
+/// An expression whose keywords may not have a field yet.
+enum Node {
+    Term {
+        keyword: String,
+        field: Option<SearchFields>,
+    },
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    Not(Box<Node>),
+}
+
+impl Node {
+    /// Gives `field` to every keyword, failing if any already has one.
+    fn bind(&mut self, new_field: &SearchFields) -> Result<(), ()> {
+        match self {
+            Self::Term { field: Some(_), .. } => Err(()),
+            Self::Term { field, .. } => {
+                *field = Some(new_field.clone());
+                Ok(())
+            }
+            Self::And(operands) | Self::Or(operands) => operands
+                .iter_mut()
+                .try_for_each(|operand| operand.bind(new_field)),
+            Self::Not(operand) => operand.bind(new_field),
+        }
+    }
+
+    fn into_expression(self) -> Result<Expression, ExpressionError> {
+        let convert_all = |operands: Vec<Self>| {
+            operands
+                .into_iter()
+                .map(Self::into_expression)
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        match self {
+            Self::Term { keyword, field } => match field {
+                Some(field) => Ok(Expression::Term {
+                    keyword: Keyword::new(keyword),
+                    field,
+                }),
+                None => Err(ExpressionError::MissingField { keyword }),
+            },
+            Self::And(operands) => convert_all(operands).map(Expression::And),
+            Self::Or(operands) => convert_all(operands).map(Expression::Or),
+            Self::Not(operand) => Ok(Expression::Not(Box::new(operand.into_expression()?))),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+    /// Parentheses and `not` entered so far, at most [`MAX_DEPTH`].
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self, expected: &'static str) -> Result<&Token, ExpressionError> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or(ExpressionError::UnexpectedEnd { expected })?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn eat_operator(&mut self, operator: &str) -> bool {
+        let found = self.peek().is_some_and(|token| token.is_operator(operator));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    /// Parses `parse` one level deeper, starting at the token at `position`.
+    fn nested(
+        &mut self,
+        position: usize,
+        parse: impl FnOnce(&mut Self) -> Result<Node, ExpressionError>,
+    ) -> Result<Node, ExpressionError> {
+        if self.depth == MAX_DEPTH {
+            return Err(ExpressionError::TooDeep { position });
+        }
+
+        self.depth += 1;
+        let node = parse(self);
+        self.depth -= 1;
+        node
+    }
+
+    fn parse_or(&mut self) -> Result<Node, ExpressionError> {
+        let mut operands = vec![self.parse_and()?];
+        while self.eat_operator("or") {
+            operands.push(self.parse_and()?);
+        }
+
+        Ok(if operands.len() == 1 {
+            operands.remove(0)
+        } else {
+            Node::Or(operands)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<Node, ExpressionError> {
+        let mut operands = vec![self.parse_not()?];
+        while self.eat_operator("and") {
+            operands.push(self.parse_not()?);
+        }
+
+        Ok(if operands.len() == 1 {
+            operands.remove(0)
+        } else {
+            Node::And(operands)
+        })
+    }
+
+    fn parse_not(&mut self) -> Result<Node, ExpressionError> {
+        if let Some(token) = self.peek()
+            && token.is_operator("not")
+        {
+            let position = token.position;
+            self.position += 1;
+            return self.nested(position, |parser| {
+                Ok(Node::Not(Box::new(parser.parse_not()?)))
+            });
+        }
+
+        self.parse_in()
+    }
+
+    fn parse_in(&mut self) -> Result<Node, ExpressionError> {
+        let mut node = self.parse_primary()?;
+
+        while let Some(token) = self.peek()
+            && token.is_operator("in")
+        {
+            let position = token.position;
+            self.position += 1;
+
+            let field = self.parse_field()?;
+            node.bind(&field)
+                .map_err(|()| ExpressionError::FieldAlreadySet { position })?;
+        }
+
+        Ok(node)
+    }
+
+    fn parse_primary(&mut self) -> Result<Node, ExpressionError> {
+        const EXPECTED: &str = "a keyword or `(`";
+
+        let token = self.next(EXPECTED)?;
+        match &token.kind {
+            TokenKind::Open => {
+                let position = token.position;
+                self.nested(position, |parser| {
+                    let node = parser.parse_or()?;
+                    match parser.next("`)`")? {
+                        Token {
+                            kind: TokenKind::Close,
+                            ..
+                        } => Ok(node),
+                        token => Err(token.unexpected("`)`")),
+                    }
+                })
+            }
+            TokenKind::Word(_)
+                if ["and", "or", "not", "in"]
+                    .iter()
+                    .any(|op| token.is_operator(op)) =>
+            {
+                Err(token.unexpected(EXPECTED))
+            }
+            TokenKind::Word(keyword) | TokenKind::Quoted(keyword) => Ok(Node::Term {
+                keyword: keyword.clone(),
+                field: None,
+            }),
+            TokenKind::Close => Err(token.unexpected(EXPECTED)),
+        }
+    }
+
+    fn parse_field(&mut self) -> Result<SearchFields, ExpressionError> {
+        const EXPECTED: &str = "a field";
+
+        let token = self.next(EXPECTED)?;
+        match &token.kind {
+            TokenKind::Word(name) => name.parse().map_err(|()| ExpressionError::UnknownField {
+                name: name.clone(),
+                position: token.position,
+            }),
+            _ => Err(token.unexpected(EXPECTED)),
+        }
+    }
+}
@@ -0,0 +1,179 @@
+use std::fmt;
+
+use regex::{RegexSet, RegexSetBuilder};
+
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(from = "serde_impls::KeywordRepr")
+)]
+pub struct Keyword {
+    pub text: String,
+    /// Where the keyword has to appear, relative to the part of the field
+    /// that differs between keys. `None` allows it anywhere in the field.
+    pub anchor: Option<Anchor>,
+    /// Overrides `search.matching.case-sensitive` for this keyword.
+    pub case_sensitive: Option<bool>,
+}
+
+impl Keyword {
+    #[must_use]
+    pub const fn new(text: String) -> Self {
+        Self {
+            text,
+            anchor: None,
+            case_sensitive: None,
+        }
+    }
+
+    /// Whether the keyword has to match with exact case, given the global default.
+    #[must_use]
+    pub const fn is_case_sensitive(&self, default: bool) -> bool {
+        match self.case_sensitive {
+            Some(case_sensitive) => case_sensitive,
+            None => default,
+        }
+    }
+
+    /// The keyword as a regular expression, wrapped to ignore case unless it
+    /// has to match with exact case.
+    #[must_use]
+    pub fn regex_pattern(&self, case_sensitive: bool) -> String {
+        if self.is_case_sensitive(case_sensitive) {
+            self.text.clone()
+        } else {
+            format!("(?i:{})", self.text)
+        }
+    }
+}
+
+/// Compiles regex keywords into the set the matcher searches a field with.
+pub fn regex_set<'a>(
+    keywords: impl IntoIterator<Item = &'a Keyword>,
+    case_sensitive: bool,
+) -> Result<RegexSet, regex::Error> {
+    RegexSetBuilder::new(
+        keywords
+            .into_iter()
+            .map(|keyword| keyword.regex_pattern(case_sensitive)),
+    )
+    .build()
+}
+
+impl From<String> for Keyword {
+    fn from(text: String) -> Self {
+        Self::new(text)
+    }
+}
+
+impl fmt::Display for Keyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.text)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(from = "serde_impls::AnchorRepr")
+)]
+pub enum Anchor {
+    /// At the very start of the varying part of the field.
+    Start,
+    /// At the very end of the varying part of the field.
+    End,
+    /// Starting exactly at the given offset.
+    Offset(usize),
+    /// Starting anywhere between `from` and `to`, inclusive.
+    Range { from: usize, to: usize },
+}
+
+impl Anchor {
+    /// Whether a keyword of `length` characters starting at `offset` of a
+    /// varying part of `variable_length` characters satisfies the anchor.
+    #[must_use]
+    pub const fn allows(&self, offset: usize, length: usize, variable_length: usize) -> bool {
+        if offset + length > variable_length {
+            return false;
+        }
+
+        match *self {
+            Self::Start => offset == 0,
+            Self::End => offset + length == variable_length,
+            Self::Offset(expected) => offset == expected,
+            Self::Range { from, to } => from <= offset && offset <= to,
+        }
+    }
+
+    /// The smallest offset the anchor allows.
+    #[must_use]
+    pub const fn min_offset(&self) -> usize {
+        match *self {
+            Self::Start | Self::End => 0,
+            Self::Offset(offset) | Self::Range { from: offset, .. } => offset,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::{Anchor, Keyword};
+
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    pub enum KeywordRepr {
+        Text(String),
+        Detailed {
+            keyword: String,
+            #[serde(default)]
+            anchor: Option<Anchor>,
+            #[serde(default, rename = "case-sensitive")]
+            case_sensitive: Option<bool>,
+        },
+    }
+
+    impl From<KeywordRepr> for Keyword {
+        fn from(repr: KeywordRepr) -> Self {
+            match repr {
+                KeywordRepr::Text(text) => Self::new(text),
+                KeywordRepr::Detailed {
+                    keyword,
+                    anchor,
+                    case_sensitive,
+                } => Self {
+                    text: keyword,
+                    anchor,
+                    case_sensitive,
+                },
+            }
+        }
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    pub enum AnchorRepr {
+        Named(NamedAnchor),
+        Offset(usize),
+        Range { from: usize, to: usize },
+    }
+
+    #[derive(serde::Deserialize)]
+    #[serde(rename_all = "kebab-case")]
+    pub enum NamedAnchor {
+        Start,
+        End,
+    }
+
+    impl From<AnchorRepr> for Anchor {
+        fn from(repr: AnchorRepr) -> Self {
+            match repr {
+                AnchorRepr::Named(NamedAnchor::Start) => Self::Start,
+                AnchorRepr::Named(NamedAnchor::End) => Self::End,
+                AnchorRepr::Offset(offset) => Self::Offset(offset),
+                AnchorRepr::Range { from, to } => Self::Range { from, to },
+            }
+        }
+    }
+}
@@ -1,9 +1,45 @@
+pub mod expression;
+pub mod keyword;
 pub mod search;
+pub mod validation;
 
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize))]
 pub struct Config {
-    pub keywords: Vec<String>,
+    /// Keywords searched in every field that doesn't list its own.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub keywords: Vec<keyword::Keyword>,
     #[cfg_attr(feature = "serde", serde(default))]
     pub search: search::Config,
 }
+
+impl Config {
+    /// Whether keywords are searched in `field`, by the expression if there is
+    /// one or by the searched fields otherwise.
+    #[must_use]
+    pub fn searches(&self, field: &search::SearchFields) -> bool {
+        match &self.search.expression {
+            Some(expression) => expression.terms().iter().any(|(_, other)| *other == field),
+            None => self
+                .search
+                .fields
+                .iter()
+                .any(|field_config| field_config.field == *field),
+        }
+    }
+
+    /// Each searched field with its keywords and matching mode, falling back
+    /// to the top-level keywords and `search.matching` where it sets none.
+    pub fn field_searches(&self) -> impl Iterator<Item = search::FieldSearch<'_>> {
+        self.search
+            .fields
+            .iter()
+            .map(|field_config| search::FieldSearch {
+                field: &field_config.field,
+                keywords: field_config.keywords.as_deref().unwrap_or(&self.keywords),
+                all_keywords: field_config
+                    .all_keywords
+                    .unwrap_or(self.search.matching.all_keywords),
+            })
+    }
+}
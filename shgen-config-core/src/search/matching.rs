@@ -7,6 +7,10 @@
 pub struct Config {
     pub all_keywords: bool,
     pub all_fields: bool,
+    pub syntax: KeywordSyntax,
+    /// Whether keywords have to match with exact case,
+    /// unless a keyword overrides it.
+    pub case_sensitive: bool,
 }
 
 impl Default for Config {
@@ -14,6 +18,22 @@ impl Default for Config {
         Self {
             all_keywords: false,
             all_fields: true,
+            syntax: KeywordSyntax::default(),
+            case_sensitive: false,
         }
     }
 }
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum KeywordSyntax {
+    /// Keywords are matched as plain substrings.
+    #[default]
+    Literal,
+    /// Keywords are regular expressions.
+    Regex,
+}
@@ -1,25 +1,72 @@
+use std::{fmt, str::FromStr};
+
+use crate::{expression::Expression, keyword::Keyword};
+
 pub mod matching;
+pub mod scoring;
 
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Deserialize), serde(default))]
 pub struct Config {
-    pub fields: Vec<SearchFields>,
+    pub fields: Vec<FieldConfig>,
     pub matching: matching::Config,
+    /// Replaces the keywords, fields, `all-keywords` and `all-fields` when set.
+    pub expression: Option<Expression>,
+    /// Ranks keys by score instead of stopping at the first match when set.
+    pub scoring: Option<scoring::Config>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            fields: vec![SearchFields::PublicKey, SearchFields::Sha256Fingerprint],
+            fields: vec![
+                SearchFields::PublicKey.into(),
+                SearchFields::Sha256Fingerprint.into(),
+            ],
             matching: matching::Config::default(),
+            expression: None,
+            scoring: None,
         }
     }
 }
 
+/// A searched field, optionally with its own keywords and matching mode.
 #[derive(Clone, Debug)]
 #[cfg_attr(
     feature = "serde",
     derive(serde::Deserialize),
+    serde(from = "serde_impls::FieldConfigRepr")
+)]
+pub struct FieldConfig {
+    pub field: SearchFields,
+    /// Keywords to search in this field instead of the top-level ones.
+    pub keywords: Option<Vec<Keyword>>,
+    /// Overrides `search.matching.all-keywords` for this field.
+    pub all_keywords: Option<bool>,
+}
+
+impl From<SearchFields> for FieldConfig {
+    fn from(field: SearchFields) -> Self {
+        Self {
+            field,
+            keywords: None,
+            all_keywords: None,
+        }
+    }
+}
+
+/// A field with the keywords to search in it, after applying the defaults.
+#[derive(Clone, Copy, Debug)]
+pub struct FieldSearch<'a> {
+    pub field: &'a SearchFields,
+    pub keywords: &'a [Keyword],
+    pub all_keywords: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize, serde::Serialize),
     serde(rename_all = "kebab-case")
 )]
 pub enum SearchFields {
@@ -30,3 +77,113 @@ pub enum SearchFields {
     Sha384Fingerprint,
     Sha512Fingerprint,
 }
+
+impl SearchFields {
+    /// Whether `character` can appear in the base64 encoded part of the field.
+    #[must_use]
+    pub const fn can_contain(&self, character: char) -> bool {
+        character.is_ascii_alphanumeric() || matches!(character, '+' | '/')
+    }
+
+    /// Length of the longest keyword that can appear in the field.
+    ///
+    /// This is the length of the base64 encoded field, or of a single
+    /// line for the private key, as keywords can't span line breaks.
+    #[must_use]
+    pub const fn max_keyword_length(&self) -> usize {
+        match self {
+            Self::PrivateKey => 70,
+            Self::PublicKey => 68,
+            Self::Sha1Fingerprint => 27,
+            Self::Sha256Fingerprint => 43,
+            Self::Sha384Fingerprint => 64,
+            Self::Sha512Fingerprint => 86,
+        }
+    }
+
+    /// Length of the part of the field that differs between keys, which
+    /// keyword anchors are relative to.
+    ///
+    /// For the public key this skips the `ssh-ed25519 AAAAC3NzaC1lZDI1NTE5AAAAI`
+    /// prefix, and for the private key it spans from the public key to the
+    /// end of the private key material, line breaks included.
+    #[must_use]
+    pub const fn variable_length(&self) -> usize {
+        match self {
+            Self::PrivateKey => 221,
+            Self::PublicKey => 43,
+            Self::Sha1Fingerprint => 27,
+            Self::Sha256Fingerprint => 43,
+            Self::Sha384Fingerprint => 64,
+            Self::Sha512Fingerprint => 86,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::{FieldConfig, SearchFields};
+    use crate::keyword::Keyword;
+
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    pub enum FieldConfigRepr {
+        Field(SearchFields),
+        Detailed {
+            field: SearchFields,
+            #[serde(default)]
+            keywords: Option<Vec<Keyword>>,
+            #[serde(default, rename = "all-keywords")]
+            all_keywords: Option<bool>,
+        },
+    }
+
+    impl From<FieldConfigRepr> for FieldConfig {
+        fn from(repr: FieldConfigRepr) -> Self {
+            match repr {
+                FieldConfigRepr::Field(field) => field.into(),
+                FieldConfigRepr::Detailed {
+                    field,
+                    keywords,
+                    all_keywords,
+                } => Self {
+                    field,
+                    keywords,
+                    all_keywords,
+                },
+            }
+        }
+    }
+}
+
+impl FromStr for SearchFields {
+    type Err = ();
+
+    /// Parses a field by its full name, or without the `-key`/`-fingerprint` suffix.
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "private-key" | "private" => Ok(Self::PrivateKey),
+            "public-key" | "public" => Ok(Self::PublicKey),
+            "sha1-fingerprint" | "sha1" => Ok(Self::Sha1Fingerprint),
+            "sha256-fingerprint" | "sha256" => Ok(Self::Sha256Fingerprint),
+            "sha384-fingerprint" | "sha384" => Ok(Self::Sha384Fingerprint),
+            "sha512-fingerprint" | "sha512" => Ok(Self::Sha512Fingerprint),
+            _ => Err(()),
+        }
+    }
+}
+
+impl fmt::Display for SearchFields {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::PrivateKey => "private-key",
+            Self::PublicKey => "public-key",
+            Self::Sha1Fingerprint => "sha1-fingerprint",
+            Self::Sha256Fingerprint => "sha256-fingerprint",
+            Self::Sha384Fingerprint => "sha384-fingerprint",
+            Self::Sha512Fingerprint => "sha512-fingerprint",
+        };
+
+        f.pad(name)
+    }
+}
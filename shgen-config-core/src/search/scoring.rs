@@ -0,0 +1,39 @@
+/// Ranks keys by a score instead of requiring a match, so that the best
+/// keys found within a budget can be kept.
+///
+/// Each weight is multiplied by its criterion in every searched field,
+/// and the results are summed into the key's score.
+#[derive(Clone, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Deserialize),
+    serde(default, rename_all = "kebab-case")
+)]
+pub struct Config {
+    /// Weight of the length of the longest keyword found.
+    pub longest_keyword: f64,
+    /// Weight of the number of distinct keywords found.
+    pub distinct_keywords: f64,
+    /// Weight of how early the first keyword is found: `1` at the start
+    /// of the varying part of the field, down to `0` at its end.
+    pub earliest_position: f64,
+    /// Weight of the length of the longest run of a repeated character.
+    pub repeated_characters: f64,
+    /// Stops the search once a key scores at least this much.
+    pub threshold: Option<f64>,
+    /// Number of best keys to keep and save.
+    pub keep: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            longest_keyword: 1.0,
+            distinct_keywords: 1.0,
+            earliest_position: 0.0,
+            repeated_characters: 0.0,
+            threshold: None,
+            keep: 1,
+        }
+    }
+}
@@ -0,0 +1,206 @@
+use crate::{
+    Config,
+    expression::Expression,
+    keyword::{self, Anchor, Keyword},
+    search::{SearchFields, matching::KeywordSyntax},
+};
+
+#[derive(Debug, thiserror::Error)]
+pub enum ValidationError {
+    #[error("At least one keyword must be specified for {field}")]
+    NoKeywords { field: SearchFields },
+    #[error("At least one search field must be specified")]
+    NoFields,
+    #[error("Keyword {keyword:?} is not a valid regular expression: {error}")]
+    InvalidRegex {
+        keyword: String,
+        error: regex::Error,
+    },
+    #[error("Keyword {keyword:?} can never match {field}: {character:?} is not a base64 character")]
+    InvalidCharacter {
+        keyword: String,
+        field: SearchFields,
+        character: char,
+    },
+    #[error(
+        "Keyword {keyword:?} can never match {field}: it is longer than {max_length} characters"
+    )]
+    TooLong {
+        keyword: String,
+        field: SearchFields,
+        max_length: usize,
+    },
+    #[error("Scoring only supports the literal keyword syntax")]
+    ScoringRegex,
+    #[error("Scoring must keep at least one key")]
+    ScoringKeepsNothing,
+    #[error("Keyword {keyword:?} has an anchor range that ends before it starts")]
+    EmptyAnchorRange { keyword: String },
+    #[error("Keyword {keyword:?} can't have an anchor with regex syntax, use `^` and `$` instead")]
+    RegexAnchor { keyword: String },
+    #[error(
+        "Keyword {keyword:?} can never match {field}: its anchor is past the {variable_length} characters that differ between keys"
+    )]
+    AnchorOutOfRange {
+        keyword: String,
+        field: SearchFields,
+        variable_length: usize,
+    },
+}
+
+impl Config {
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        if let Some(scoring) = &self.search.scoring {
+            if self.search.matching.syntax == KeywordSyntax::Regex {
+                return Err(ValidationError::ScoringRegex);
+            }
+
+            if scoring.keep == 0 {
+                return Err(ValidationError::ScoringKeepsNothing);
+            }
+        }
+
+        if let Some(expression) = &self.search.expression {
+            return self.validate_expression(expression);
+        }
+
+        if self.search.fields.is_empty() {
+            return Err(ValidationError::NoFields);
+        }
+
+        for search in self.field_searches() {
+            let field = search.field.clone();
+
+            // Scoring can rank keys by their characters alone.
+            if search.keywords.is_empty() && self.search.scoring.is_none() {
+                return Err(ValidationError::NoKeywords { field });
+            }
+
+            match self.search.matching.syntax {
+                KeywordSyntax::Literal => {
+                    for keyword in search.keywords {
+                        validate_keyword(keyword, &field)?;
+                    }
+                }
+                KeywordSyntax::Regex => {
+                    let keywords: Vec<&Keyword> = search.keywords.iter().collect();
+                    validate_regexes(&keywords, self.search.matching.case_sensitive)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn validate_expression(&self, expression: &Expression) -> Result<(), ValidationError> {
+        if self.search.matching.syntax == KeywordSyntax::Literal {
+            for (keyword, field) in expression.terms() {
+                validate_keyword(keyword, field)?;
+            }
+            return Ok(());
+        }
+
+        // The matcher compiles the distinct keywords of each field together.
+        let mut field_keywords: Vec<(&SearchFields, Vec<&Keyword>)> = Vec::new();
+        for (keyword, field) in expression.terms() {
+            let keywords = match field_keywords.iter().position(|(other, _)| *other == field) {
+                Some(index) => &mut field_keywords[index].1,
+                None => {
+                    field_keywords.push((field, Vec::new()));
+                    &mut field_keywords.last_mut().unwrap().1
+                }
+            };
+            if !keywords.iter().any(|other| other.text == keyword.text) {
+                keywords.push(keyword);
+            }
+        }
+
+        for (_, keywords) in &field_keywords {
+            validate_regexes(keywords, self.search.matching.case_sensitive)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_keyword(keyword: &Keyword, field: &SearchFields) -> Result<(), ValidationError> {
+    let Keyword {
+        text: keyword,
+        anchor,
+        ..
+    } = keyword;
+
+    if let Some(character) = keyword
+        .chars()
+        .find(|&character| !field.can_contain(character))
+    {
+        return Err(ValidationError::InvalidCharacter {
+            keyword: keyword.clone(),
+            field: field.clone(),
+            character,
+        });
+    }
+
+    let max_length = field.max_keyword_length();
+    if keyword.len() > max_length {
+        return Err(ValidationError::TooLong {
+            keyword: keyword.clone(),
+            field: field.clone(),
+            max_length,
+        });
+    }
+
+    if let Some(anchor) = anchor {
+        if let Anchor::Range { from, to } = anchor
+            && from > to
+        {
+            return Err(ValidationError::EmptyAnchorRange {
+                keyword: keyword.clone(),
+            });
+        }
+
+        let variable_length = field.variable_length();
+        if anchor.min_offset() + keyword.len() > variable_length {
+            return Err(ValidationError::AnchorOutOfRange {
+                keyword: keyword.clone(),
+                field: field.clone(),
+                variable_length,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+/// Compiles the regex keywords of a field the same way the matcher does, so
+/// that patterns over the size limits fail here instead of in the matcher.
+fn validate_regexes(keywords: &[&Keyword], case_sensitive: bool) -> Result<(), ValidationError> {
+    let invalid = |keyword: &Keyword, error| ValidationError::InvalidRegex {
+        keyword: keyword.text.clone(),
+        error,
+    };
+
+    for &keyword in keywords {
+        if keyword.anchor.is_some() {
+            return Err(ValidationError::RegexAnchor {
+                keyword: keyword.text.clone(),
+            });
+        }
+
+        keyword::regex_set([keyword], case_sensitive).map_err(|error| invalid(keyword, error))?;
+    }
+
+    if let Err(error) = keyword::regex_set(keywords.iter().copied(), case_sensitive) {
+        // Every keyword compiles alone, so find the one that makes the set
+        // too big.
+        let culprit = (1..keywords.len())
+            .find(|&count| {
+                keyword::regex_set(keywords[..count].iter().copied(), case_sensitive).is_err()
+            })
+            .unwrap_or(keywords.len())
+            - 1;
+        return Err(invalid(keywords[culprit], error));
+    }
+
+    Ok(())
+}
@@ -0,0 +1,20 @@
+use std::time::Duration;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    #[serde(with = "humantime_serde")]
+    pub duration: Duration,
+    pub keys: Option<u64>,
+    pub threads: Vec<usize>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            duration: Duration::from_secs(3),
+            keys: None,
+            threads: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,156 @@
+use std::{fmt, path::PathBuf, str::FromStr, time::Duration};
+
+/// Deterministic searches that can be resumed with `--resume`.
+#[derive(Debug, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    /// Where progress is saved. Anyone with this file can recreate the keys.
+    pub path: PathBuf,
+    /// How often progress is saved.
+    #[serde(with = "humantime_serde")]
+    pub interval: Duration,
+    /// Master seed every worker derives its keys from. Random if not set.
+    pub seed: Option<Seed>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("shgen-checkpoint.json"),
+            interval: Duration::from_secs(60),
+            seed: None,
+        }
+    }
+}
+
+/// 32-byte master seed, written as 64 hex characters.
+#[derive(Clone, Copy, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Seed(pub [u8; 32]);
+
+#[derive(Debug, thiserror::Error)]
+#[error("Seed must be 64 hex characters")]
+pub struct InvalidSeed;
+
+impl FromStr for Seed {
+    type Err = InvalidSeed;
+
+    fn from_str(hex: &str) -> Result<Self, Self::Err> {
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err(InvalidSeed);
+        }
+
+        let mut seed = [0u8; 32];
+        for (byte, digits) in seed.iter_mut().zip(hex.as_bytes().chunks_exact(2)) {
+            let digits = std::str::from_utf8(digits).map_err(|_| InvalidSeed)?;
+            *byte = u8::from_str_radix(digits, 16).map_err(|_| InvalidSeed)?;
+        }
+
+        Ok(Self(seed))
+    }
+}
+
+impl TryFrom<String> for Seed {
+    type Error = InvalidSeed;
+
+    fn try_from(hex: String) -> Result<Self, Self::Error> {
+        hex.parse()
+    }
+}
+
+impl From<Seed> for String {
+    fn from(seed: Seed) -> Self {
+        seed.to_string()
+    }
+}
+
+impl fmt::Display for Seed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+    }
+}
+
+/// Keeps the seed out of logs and panic messages.
+impl fmt::Debug for Seed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Seed(..)")
+    }
+}
+
+/// Progress of a deterministic search, saved to resume it later.
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Checkpoint {
+    pub seed: Seed,
+    /// Hash of the keywords and search settings the progress applies to.
+    pub search_hash: String,
+    /// Keys every worker checked, each from its own stream of the seed.
+    pub attempts: Vec<u64>,
+    /// Matches saved so far.
+    pub matches: u64,
+}
+
+#[cfg(feature = "fs")]
+mod fs_impls {
+    use super::Checkpoint;
+
+    use std::{
+        fs,
+        io::{self, Write as _},
+        path::Path,
+    };
+
+    #[derive(Debug, thiserror::Error)]
+    pub enum CheckpointError {
+        #[error("Failed to access checkpoint {path}: {source}")]
+        Io { path: String, source: io::Error },
+        #[error("Checkpoint {path} is invalid: {source}")]
+        Invalid {
+            path: String,
+            source: serde_json::Error,
+        },
+    }
+
+    impl Checkpoint {
+        pub fn load(path: &Path) -> Result<Self, CheckpointError> {
+            let contents = fs::read(path).map_err(|source| CheckpointError::Io {
+                path: path.display().to_string(),
+                source,
+            })?;
+
+            serde_json::from_slice(&contents).map_err(|source| CheckpointError::Invalid {
+                path: path.display().to_string(),
+                source,
+            })
+        }
+
+        /// Replaces the checkpoint at `path`, through a temporary file so that
+        /// a crash while saving keeps the previous checkpoint.
+        pub fn save(&self, path: &Path) -> Result<(), CheckpointError> {
+            let io_error = |source| CheckpointError::Io {
+                path: path.display().to_string(),
+                source,
+            };
+
+            let contents = serde_json::to_vec_pretty(self).expect("checkpoint is serializable");
+
+            let mut temporary_path = path.as_os_str().to_owned();
+            temporary_path.push(".tmp");
+
+            let mut options = fs::OpenOptions::new();
+            options.write(true).create(true).truncate(true);
+            #[cfg(unix)]
+            std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+            let mut file = options.open(&temporary_path).map_err(io_error)?;
+            file.write_all(&contents).map_err(io_error)?;
+            file.sync_all().map_err(io_error)?;
+            drop(file);
+
+            fs::rename(&temporary_path, path).map_err(io_error)
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+pub use fs_impls::CheckpointError;
@@ -1,4 +1,7 @@
+pub mod benchmark;
+pub mod checkpoint;
 pub mod output;
+pub mod report;
 pub mod runtime;
 
 #[derive(Debug, serde::Deserialize)]
@@ -9,20 +12,41 @@ pub struct Config {
     pub runtime: runtime::Config,
     #[serde(default)]
     pub output: output::Config,
+    #[serde(default)]
+    pub benchmark: benchmark::Config,
+    /// Hash of the keywords and search settings, telling whether a checkpoint
+    /// belongs to the same search.
+    #[serde(skip)]
+    pub search_hash: String,
 }
 
 #[cfg(feature = "fs")]
 mod fs_impls {
-    use super::Config;
+    use super::{Config, runtime};
 
     use std::path::PathBuf;
 
     use figment::providers::Format as _;
-    use figment::{Figment, providers::Yaml};
+    use figment::{
+        Figment,
+        providers::{Serialized, Yaml},
+    };
+    use sha2::{Digest as _, Sha256};
+    use shgen_config_core::search::SearchFields;
 
     impl Config {
-        pub fn load(config_path: PathBuf) -> Result<Self, Box<figment::Error>> {
-            let config: Self = Figment::new().merge(Yaml::file(config_path)).extract()?;
+        /// Loads the config file, with `expression` replacing `search.expression` if given.
+        pub fn load(
+            config_path: PathBuf,
+            expression: Option<String>,
+        ) -> Result<Self, Box<figment::Error>> {
+            let mut figment = Figment::new().merge(Yaml::file(config_path));
+            if let Some(expression) = expression {
+                figment = figment.merge(Serialized::default("search.expression", expression));
+            }
+
+            let mut config: Self = figment.extract()?;
+            config.search_hash = search_hash(&figment);
 
             config.validate()?;
             Ok(config)
@@ -35,6 +59,50 @@ mod fs_impls {
                 )));
             }
 
+            if let Some(filename) = &self.output.filename
+                && (filename.is_empty()
+                    || filename == "."
+                    || filename == ".."
+                    || filename.contains(['/', '\\', '\0']))
+            {
+                return Err(Box::new(figment::Error::from(
+                    "Filename must be a file name, without a directory",
+                )));
+            }
+
+            if self.output.comment.contains(['\n', '\r']) {
+                return Err(Box::new(figment::Error::from(
+                    "Comment must be a single line",
+                )));
+            }
+
+            // The comment is stored in the private key, changing its length
+            // fields inside the text that was searched.
+            if !self.output.comment.is_empty() && self.shared.searches(&SearchFields::PrivateKey) {
+                return Err(Box::new(figment::Error::from(
+                    "A comment can't be used while searching the private key, as it changes the key's text",
+                )));
+            }
+
+            if self
+                .output
+                .encryption
+                .as_ref()
+                .is_some_and(|encryption| encryption.rounds == 0)
+            {
+                return Err(Box::new(figment::Error::from(
+                    "Encryption rounds must be greater than 0",
+                )));
+            }
+
+            // The saved file wouldn't contain the keywords found in the
+            // unencrypted text.
+            if self.output.encryption.is_some() && self.shared.searches(&SearchFields::PrivateKey) {
+                return Err(Box::new(figment::Error::from(
+                    "Encryption can't be used while searching the private key, as encrypting it changes its text",
+                )));
+            }
+
             if self.runtime.threads == 0 {
                 return Err(Box::new(figment::Error::from(
                     "Number of threads must be greater than 0",
@@ -47,19 +115,98 @@ mod fs_impls {
                 )));
             }
 
-            if self.shared.keywords.is_empty() {
+            if self.runtime.progress_interval.is_zero() {
+                return Err(Box::new(figment::Error::from(
+                    "Progress interval must be greater than 0",
+                )));
+            }
+
+            if self.runtime.max_matches == runtime::MaxMatches::Count(0) {
                 return Err(Box::new(figment::Error::from(
-                    "At least one keyword must be specified",
+                    "Maximum number of matches must be greater than 0",
                 )));
             }
 
-            if self.shared.keywords.len() > 64 {
+            if self
+                .runtime
+                .timeout
+                .is_some_and(|timeout| timeout.is_zero())
+            {
                 return Err(Box::new(figment::Error::from(
-                    "Number of keywords must be less than or equal to 64",
+                    "Timeout must be greater than 0",
                 )));
             }
 
+            if self.runtime.max_attempts == Some(0) {
+                return Err(Box::new(figment::Error::from(
+                    "Maximum number of attempts must be greater than 0",
+                )));
+            }
+
+            if let Some(scoring) = &self.shared.search.scoring
+                && scoring.threshold.is_none()
+                && self.runtime.timeout.is_none()
+                && self.runtime.max_attempts.is_none()
+            {
+                return Err(Box::new(figment::Error::from(
+                    "Scoring needs a score threshold, a timeout or a maximum number of attempts to stop",
+                )));
+            }
+
+            if let Some(checkpoint) = &self.runtime.checkpoint {
+                if checkpoint.interval.is_zero() {
+                    return Err(Box::new(figment::Error::from(
+                        "Checkpoint interval must be greater than 0",
+                    )));
+                }
+
+                if self.shared.search.scoring.is_some() {
+                    return Err(Box::new(figment::Error::from(
+                        "Checkpoints are not supported with scoring",
+                    )));
+                }
+            }
+
+            if self.benchmark.duration.is_zero() {
+                return Err(Box::new(figment::Error::from(
+                    "Benchmark duration must be greater than 0",
+                )));
+            }
+
+            if self.benchmark.keys == Some(0) {
+                return Err(Box::new(figment::Error::from(
+                    "Number of benchmark keys must be greater than 0",
+                )));
+            }
+
+            if self
+                .benchmark
+                .threads
+                .iter()
+                .any(|&threads| threads == 0 || threads > 192)
+            {
+                return Err(Box::new(figment::Error::from(
+                    "Number of benchmark threads must be between 1 and 192",
+                )));
+            }
+
+            self.shared
+                .validate()
+                .map_err(|error| Box::new(figment::Error::from(error.to_string())))?;
+
             Ok(())
         }
     }
+
+    /// SHA-256 of the `keywords` and `search` sections as loaded, in hex.
+    fn search_hash(figment: &Figment) -> String {
+        let keywords = figment.find_value("keywords").ok();
+        let search = figment.find_value("search").ok();
+        let json = serde_json::to_vec(&(keywords, search)).expect("config is serializable");
+
+        Sha256::digest(json)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
 }
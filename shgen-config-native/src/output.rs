@@ -4,35 +4,347 @@ use std::path::PathBuf;
 #[serde(default, rename_all = "kebab-case")]
 pub struct Config {
     pub save_to: PathBuf,
+    /// Name of saved private keys, with `.pub` added for public keys.
+    /// `{n}`, `{keyword}`, `{fingerprint}` and `{fingerprint8}` are replaced
+    /// by the number of the match, the keywords found, and the whole or
+    /// first 8 characters of the SHA-256 fingerprint. Defaults to
+    /// `id_ed25519`, or `id_ed25519_{n}` when more than one key is saved,
+    /// and a name that renders empty is `id_ed25519`.
+    pub filename: Option<String>,
+    /// Comment of saved keys, in both the private key and the public key
+    /// line. `{user}`, `{host}`, `{date}` and `{keyword}` are replaced by
+    /// the user name, the host name, the date and the keywords found.
+    pub comment: String,
+    /// What to do when a key file to save already exists.
+    pub existing: Existing,
+    /// File that a line of JSON describing every saved key is appended to.
+    pub report: Option<PathBuf>,
+    /// Encrypts saved private keys with a passphrase, so that found keys
+    /// never touch the disk unencrypted.
+    pub encryption: Option<Encryption>,
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
             save_to: PathBuf::from("found-keys"),
+            filename: None,
+            comment: String::new(),
+            existing: Existing::default(),
+            report: None,
+            encryption: None,
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Existing {
+    /// Stops instead of saving, so that a key in use is never replaced.
+    #[default]
+    Refuse,
+    /// Saves as `<name>-2`, `<name>-3` and so on instead.
+    Suffix,
+    /// Replaces existing files, the same as `--force`.
+    Overwrite,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Encryption {
+    pub cipher: Cipher,
+    /// Rounds of the bcrypt key derivation. More rounds make the passphrase
+    /// slower to guess, and the key slower to load.
+    pub rounds: u32,
+    pub passphrase: Passphrase,
+}
+
+impl Default for Encryption {
+    fn default() -> Self {
+        Self {
+            cipher: Cipher::default(),
+            // The same as `ssh-keygen -a` defaults to.
+            rounds: 16,
+            passphrase: Passphrase::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Deserialize)]
+pub enum Cipher {
+    #[default]
+    #[serde(rename = "aes256-ctr")]
+    Aes256Ctr,
+    #[serde(rename = "aes256-gcm@openssh.com")]
+    Aes256Gcm,
+}
+
+/// Where the passphrase comes from. It is read once, before the search.
+#[derive(Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Passphrase {
+    /// The value of an environment variable.
+    Env(String),
+    /// The first line of a file.
+    File(PathBuf),
+    /// Asked for on the terminal, twice.
+    #[default]
+    Prompt,
+}
+
 #[cfg(feature = "fs")]
 mod fs_impls {
-    use super::Config;
+    use super::{Config, Existing, Passphrase};
+
+    use std::{
+        fs,
+        io::{self, Write},
+        path::{Path, PathBuf},
+    };
 
     use shgen_types::{OpenSSHPrivateKey, OpenSSHPublicKey};
+    use zeroize::Zeroizing;
+
+    #[derive(Debug, thiserror::Error)]
+    pub enum PassphraseError {
+        #[error("Environment variable {0} with the passphrase is not set")]
+        MissingVariable(String),
+        #[error("Failed to read passphrase from {path}: {source}")]
+        File { path: String, source: io::Error },
+        #[error("Failed to read passphrase: {0}")]
+        Prompt(io::Error),
+        #[error("Passphrases don't match")]
+        Mismatch,
+        #[error("Passphrase must not be empty")]
+        Empty,
+    }
+
+    #[derive(Debug, thiserror::Error)]
+    pub enum SaveError {
+        #[error(
+            "{0} already exists; pass --force to overwrite it, or set `output.existing: suffix`"
+        )]
+        Exists(String),
+        #[error("Failed to save {path}: {source}")]
+        Io { path: String, source: io::Error },
+    }
+
+    impl Passphrase {
+        pub fn read(&self) -> Result<Zeroizing<String>, PassphraseError> {
+            let passphrase = match self {
+                Self::Env(variable) => Zeroizing::new(
+                    std::env::var(variable)
+                        .map_err(|_| PassphraseError::MissingVariable(variable.clone()))?,
+                ),
+                Self::File(path) => {
+                    let contents =
+                        Zeroizing::new(std::fs::read_to_string(path).map_err(|source| {
+                            PassphraseError::File {
+                                path: path.display().to_string(),
+                                source,
+                            }
+                        })?);
+                    Zeroizing::new(contents.lines().next().unwrap_or_default().to_owned())
+                }
+                Self::Prompt => {
+                    let passphrase = Zeroizing::new(
+                        rpassword::prompt_password("Passphrase for found keys: ")
+                            .map_err(PassphraseError::Prompt)?,
+                    );
+                    let confirmation = Zeroizing::new(
+                        rpassword::prompt_password("Same passphrase again: ")
+                            .map_err(PassphraseError::Prompt)?,
+                    );
+                    if passphrase != confirmation {
+                        return Err(PassphraseError::Mismatch);
+                    }
+                    passphrase
+                }
+            };
+
+            if passphrase.is_empty() {
+                return Err(PassphraseError::Empty);
+            }
+
+            Ok(passphrase)
+        }
+    }
 
     impl Config {
-        pub fn save_keys(&self, public_key: &OpenSSHPublicKey, private_key: &OpenSSHPrivateKey) {
-            let save_dir = &self.save_to;
+        /// Saves the key pair as `<name>` and `<name>.pub` in the output
+        /// directory, returning the path of the private key. The private
+        /// key is only readable by its owner, and each file appears whole
+        /// or not at all.
+        ///
+        /// Unless overwriting, a name whose files exist by now gets the first
+        /// free `-2`, `-3` and so on suffix instead, so that a found key is
+        /// never lost.
+        pub fn save_keys(
+            &self,
+            name: &str,
+            public_key: &OpenSSHPublicKey,
+            private_key: &OpenSSHPrivateKey,
+        ) -> Result<PathBuf, SaveError> {
+            create_dir(&self.save_to)?;
+
+            let path = if self.existing == Existing::Overwrite {
+                self.write_keys(name, public_key, private_key, true)?
+            } else {
+                self.write_new_keys(name, public_key, private_key)?
+            };
+
+            println!("Saved keys to {}", path.display());
+            Ok(path)
+        }
+
+        /// Writes the key pair under the first free name of [`suffixed`].
+        fn write_new_keys(
+            &self,
+            name: &str,
+            public_key: &OpenSSHPublicKey,
+            private_key: &OpenSSHPrivateKey,
+        ) -> Result<PathBuf, SaveError> {
+            for candidate in suffixed(name) {
+                if self.taken(&candidate).is_some() {
+                    continue;
+                }
 
-            std::fs::create_dir_all(save_dir).expect("failed to create output directory");
+                // The files may still appear between the check and the write.
+                match self.write_keys(&candidate, public_key, private_key, false) {
+                    Err(SaveError::Exists(_)) => {}
+                    Ok(path) => {
+                        if self.existing == Existing::Refuse && candidate != name {
+                            eprintln!(
+                                "{} already exists, saving the found key as {candidate} instead",
+                                self.save_to.join(name).display()
+                            );
+                        }
+                        return Ok(path);
+                    }
+                    Err(error) => return Err(error),
+                }
+            }
 
-            let public_key_path = save_dir.join("id_ed25519.pub");
-            let private_key_path = save_dir.join("id_ed25519");
+            unreachable!("some suffix is free")
+        }
+
+        fn write_keys(
+            &self,
+            name: &str,
+            public_key: &OpenSSHPublicKey,
+            private_key: &OpenSSHPrivateKey,
+            overwrite: bool,
+        ) -> Result<PathBuf, SaveError> {
+            let public_key_path = self.save_to.join(format!("{name}.pub"));
+            let private_key_path = self.save_to.join(name);
+
+            // The private key goes first, so that a public key is never left
+            // without it.
+            write_file(&private_key_path, private_key.as_bytes(), 0o600, overwrite)?;
+            if let Err(error) =
+                write_file(&public_key_path, public_key.as_bytes(), 0o644, overwrite)
+            {
+                // Without overwriting, the private key was just created here.
+                if !overwrite {
+                    let _ = fs::remove_file(&private_key_path);
+                }
+                return Err(error);
+            }
 
-            std::fs::write(&public_key_path, &**public_key).expect("failed to write public key");
-            std::fs::write(&private_key_path, &**private_key).expect("failed to write private key");
+            Ok(private_key_path)
+        }
+
+        /// Checks that keys can be saved as `name`, so that a search whose
+        /// result would be refused doesn't start.
+        pub fn check_available(&self, name: &str) -> Result<(), SaveError> {
+            match self.taken(name) {
+                Some(path) if self.existing == Existing::Refuse => {
+                    Err(SaveError::Exists(path.display().to_string()))
+                }
+                _ => Ok(()),
+            }
+        }
 
-            println!("Saved keys to {}", save_dir.display());
+        /// The first of the files of the key `name` that exists.
+        fn taken(&self, name: &str) -> Option<PathBuf> {
+            [name.to_owned(), format!("{name}.pub")]
+                .into_iter()
+                .map(|file_name| self.save_to.join(file_name))
+                .find(|path| path.symlink_metadata().is_ok())
         }
     }
+
+    /// `name`, then `name` with a `-2`, `-3` and so on suffix.
+    fn suffixed(name: &str) -> impl Iterator<Item = String> {
+        (1..).map(move |n| match n {
+            1 => name.to_owned(),
+            n => format!("{name}-{n}"),
+        })
+    }
+
+    /// Creates the output directory, only accessible by its owner.
+    fn create_dir(path: &Path) -> Result<(), SaveError> {
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+
+        builder.create(path).map_err(|source| SaveError::Io {
+            path: path.display().to_string(),
+            source,
+        })
+    }
+
+    /// Writes `contents` to a temporary file with `mode`, then moves it to
+    /// `path`. Unless `overwrite` is set, an existing file is left alone.
+    fn write_file(
+        path: &Path,
+        contents: &[u8],
+        mode: u32,
+        overwrite: bool,
+    ) -> Result<(), SaveError> {
+        let file_name = path
+            .file_name()
+            .expect("key path has a file name")
+            .to_string_lossy();
+        let temp_path = path.with_file_name(format!(".{file_name}.{}.tmp", std::process::id()));
+
+        let mut options = fs::OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, mode);
+        #[cfg(not(unix))]
+        let _ = mode;
+
+        let result = options
+            .open(&temp_path)
+            .and_then(|mut file| {
+                file.write_all(contents)?;
+                file.sync_all()
+            })
+            .and_then(|()| {
+                if overwrite {
+                    fs::rename(&temp_path, path)
+                } else {
+                    // Unlike a rename, a link never replaces an existing file.
+                    fs::hard_link(&temp_path, path)?;
+                    fs::remove_file(&temp_path)
+                }
+            });
+
+        result.map_err(|source| {
+            let _ = fs::remove_file(&temp_path);
+            if !overwrite && source.kind() == io::ErrorKind::AlreadyExists && path.exists() {
+                SaveError::Exists(path.display().to_string())
+            } else {
+                SaveError::Io {
+                    path: path.display().to_string(),
+                    source,
+                }
+            }
+        })
+    }
 }
+
+#[cfg(feature = "fs")]
+pub use fs_impls::{PassphraseError, SaveError};
@@ -0,0 +1,110 @@
+use std::path::PathBuf;
+
+use shgen_config_core::search::SearchFields;
+
+/// A saved key, written to `output.report` as one line of JSON.
+#[derive(Debug, serde::Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct Record {
+    /// The private key file. The public key is next to it, with `.pub`.
+    pub path: PathBuf,
+    /// The public key line, with its comment.
+    pub public_key: String,
+    pub fingerprints: Fingerprints,
+    pub matches: Vec<KeywordMatch>,
+    /// Score of the key, when scoring keys.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f64>,
+    /// Keys checked by all threads when the key was saved.
+    pub attempts: u64,
+    /// Seconds since the search started.
+    pub elapsed: f64,
+    pub thread_id: usize,
+    /// Hash of the keywords and search settings, the same as in checkpoints.
+    pub search_hash: String,
+}
+
+/// Fingerprints as `ssh-keygen -l` shows them, such as `SHA256:...`.
+#[derive(Debug, serde::Serialize)]
+pub struct Fingerprints {
+    pub sha1: String,
+    pub sha256: String,
+    pub sha384: String,
+    pub sha512: String,
+}
+
+#[derive(Debug, serde::Serialize)]
+pub struct KeywordMatch {
+    pub keyword: String,
+    pub field: SearchFields,
+    /// Byte offsets of every occurrence in the field, without the
+    /// `SHA256:` style prefix of fingerprints.
+    pub offsets: Vec<usize>,
+}
+
+#[cfg(feature = "fs")]
+mod fs_impls {
+    use super::Record;
+
+    use std::{
+        fs,
+        io::{self, Write as _},
+        path::{Path, PathBuf},
+    };
+
+    #[derive(Debug, thiserror::Error)]
+    #[error("Failed to write report {path}: {source}")]
+    pub struct ReportError {
+        path: String,
+        source: io::Error,
+    }
+
+    /// The `output.report` file, which records are appended to.
+    pub struct Report {
+        file: fs::File,
+        path: PathBuf,
+    }
+
+    impl Report {
+        /// Opens the report at `path`, creating it and its directory if
+        /// needed.
+        pub fn open(path: &Path) -> Result<Self, ReportError> {
+            let io_error = |source| ReportError {
+                path: path.display().to_string(),
+                source,
+            };
+
+            if let Some(parent) = path.parent()
+                && !parent.as_os_str().is_empty()
+            {
+                fs::create_dir_all(parent).map_err(io_error)?;
+            }
+
+            let file = fs::OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)
+                .map_err(io_error)?;
+
+            Ok(Self {
+                file,
+                path: path.to_owned(),
+            })
+        }
+
+        /// Appends `record` as a single line, in one write so that lines
+        /// from several searches don't interleave.
+        pub fn append(&self, record: &Record) -> Result<(), ReportError> {
+            let mut line = serde_json::to_vec(record).expect("record is serializable");
+            line.push(b'\n');
+
+            (&self.file).write_all(&line).map_err(|source| ReportError {
+                path: self.path.display().to_string(),
+                source,
+            })
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+pub use fs_impls::{Report, ReportError};
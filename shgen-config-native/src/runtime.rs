@@ -1,8 +1,24 @@
+use std::time::Duration;
+
+use crate::checkpoint;
+
 #[derive(Debug, serde::Deserialize)]
 #[serde(default, rename_all = "kebab-case")]
 pub struct Config {
     pub threads: usize,
     pub keep_awake: bool,
+    #[serde(with = "humantime_serde")]
+    pub progress_interval: Duration,
+    /// Number of matching keys to find before stopping.
+    pub max_matches: MaxMatches,
+    /// Stops the search after this long, keeping the matches found so far.
+    #[serde(with = "humantime_serde")]
+    pub timeout: Option<Duration>,
+    /// Stops the search after trying about this many keys.
+    pub max_attempts: Option<u64>,
+    /// Derives keys from a master seed and saves progress, so that the
+    /// search can be resumed.
+    pub checkpoint: Option<checkpoint::Config>,
 }
 
 impl Default for Config {
@@ -12,6 +28,51 @@ impl Default for Config {
                 .map(std::num::NonZeroUsize::get)
                 .unwrap_or(1),
             keep_awake: true,
+            progress_interval: Duration::from_secs(1),
+            max_matches: MaxMatches::Count(1),
+            timeout: None,
+            max_attempts: None,
+            checkpoint: None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize)]
+#[serde(from = "MaxMatchesRepr")]
+pub enum MaxMatches {
+    Count(u64),
+    /// Keeps searching until the timeout or until interrupted.
+    Unlimited,
+}
+
+impl MaxMatches {
+    #[must_use]
+    pub const fn is_reached(self, matches: u64) -> bool {
+        match self {
+            Self::Count(count) => matches >= count,
+            Self::Unlimited => false,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(untagged)]
+enum MaxMatchesRepr {
+    Count(u64),
+    Named(NamedMaxMatches),
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum NamedMaxMatches {
+    Unlimited,
+}
+
+impl From<MaxMatchesRepr> for MaxMatches {
+    fn from(repr: MaxMatchesRepr) -> Self {
+        match repr {
+            MaxMatchesRepr::Count(count) => Self::Count(count),
+            MaxMatchesRepr::Named(NamedMaxMatches::Unlimited) => Self::Unlimited,
         }
     }
 }
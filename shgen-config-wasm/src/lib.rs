@@ -9,28 +9,128 @@ use crate::macros::core_enum_to_wasm;
 core_to_wasm_wrapper! {
     #[derive(Debug)]
     pub struct Config(shgen_config_core::Config);
-    constructor(keywords: Vec<String>, search: SearchConfig) {
+    constructor(keywords: Vec<Keyword>, search: SearchConfig) {
         Self(shgen_config_core::Config {
-            keywords,
+            keywords: keywords.into_iter().map(Into::into).collect(),
             search: search.into(),
         })
     }
 
     getters {
         #[must_use]
-        keywords -> Vec<String> => |config| config.0.keywords.clone();
+        keywords -> Vec<Keyword> => |config| config.0.keywords.iter().cloned().map(Into::into).collect();
         #[must_use]
         search -> SearchConfig => |config| config.0.search.clone().into();
     }
 }
 
+#[wasm_bindgen]
+impl Config {
+    /// Checks that the keywords can match the selected fields.
+    pub fn validate(&self) -> Result<(), JsError> {
+        self.0
+            .validate()
+            .map_err(|error| JsError::new(&error.to_string()))
+    }
+}
+
+core_to_wasm_wrapper! {
+    #[derive(Debug)]
+    pub struct Keyword(shgen_config_core::keyword::Keyword);
+    constructor(text: String, anchor: Option<Anchor>, case_sensitive: Option<bool>) {
+        Self(shgen_config_core::keyword::Keyword {
+            text,
+            anchor: anchor.map(Into::into),
+            case_sensitive,
+        })
+    }
+
+    getters {
+        #[must_use]
+        text -> String => |keyword| keyword.0.text.clone();
+    }
+}
+
+core_to_wasm_wrapper! {
+    #[derive(Debug)]
+    pub struct Anchor(shgen_config_core::keyword::Anchor);
+}
+
+#[wasm_bindgen]
+impl Anchor {
+    #[must_use]
+    pub fn start() -> Self {
+        Self(shgen_config_core::keyword::Anchor::Start)
+    }
+
+    #[must_use]
+    pub fn end() -> Self {
+        Self(shgen_config_core::keyword::Anchor::End)
+    }
+
+    #[must_use]
+    pub fn offset(offset: usize) -> Self {
+        Self(shgen_config_core::keyword::Anchor::Offset(offset))
+    }
+
+    #[must_use]
+    pub fn range(from: usize, to: usize) -> Self {
+        Self(shgen_config_core::keyword::Anchor::Range { from, to })
+    }
+}
+
 core_to_wasm_wrapper! {
     #[derive(Debug)]
     pub struct SearchConfig(shgen_config_core::search::Config);
     constructor(fields: Vec<SearchFields>, matching: MatchingConfig) {
+        Self(shgen_config_core::search::Config {
+            fields: fields
+                .into_iter()
+                .map(|field| shgen_config_core::search::SearchFields::from(field).into())
+                .collect(),
+            matching: matching.into(),
+            expression: None,
+            scoring: None,
+        })
+    }
+}
+
+#[wasm_bindgen]
+impl SearchConfig {
+    /// Searches for a boolean expression such as `cat in public-key and not dog in sha256`.
+    #[wasm_bindgen(js_name = withExpression)]
+    pub fn with_expression(expression: &str, matching: MatchingConfig) -> Result<Self, JsError> {
+        Ok(Self(shgen_config_core::search::Config {
+            fields: Vec::new(),
+            matching: matching.into(),
+            expression: Some(expression.parse()?),
+            scoring: None,
+        }))
+    }
+
+    /// Searches fields that may each have their own keywords and matching mode.
+    #[wasm_bindgen(js_name = withFieldConfigs)]
+    #[must_use]
+    pub fn with_field_configs(fields: Vec<FieldConfig>, matching: MatchingConfig) -> Self {
         Self(shgen_config_core::search::Config {
             fields: fields.into_iter().map(Into::into).collect(),
             matching: matching.into(),
+            expression: None,
+            scoring: None,
+        })
+    }
+}
+
+// An empty `keywords` list uses the top-level keywords instead.
+core_to_wasm_wrapper! {
+    #[derive(Debug)]
+    pub struct FieldConfig(shgen_config_core::search::FieldConfig);
+    constructor(field: SearchFields, keywords: Vec<Keyword>, all_keywords: Option<bool>) {
+        Self(shgen_config_core::search::FieldConfig {
+            field: field.into(),
+            keywords: (!keywords.is_empty())
+                .then(|| keywords.into_iter().map(Into::into).collect()),
+            all_keywords,
         })
     }
 }
@@ -38,10 +138,12 @@ core_to_wasm_wrapper! {
 core_to_wasm_wrapper! {
     #[derive(Debug)]
     pub struct MatchingConfig(shgen_config_core::search::matching::Config);
-    constructor(all_keywords: bool, all_fields: bool) {
+    constructor(all_keywords: bool, all_fields: bool, syntax: KeywordSyntax, case_sensitive: bool) {
         Self(shgen_config_core::search::matching::Config {
             all_keywords,
             all_fields,
+            syntax: syntax.into(),
+            case_sensitive,
         })
     }
 }
@@ -56,3 +158,10 @@ core_enum_to_wasm! {
         Sha512Fingerprint,
     }
 }
+
+core_enum_to_wasm! {
+    pub enum KeywordSyntax => shgen_config_core::search::matching::KeywordSyntax {
+        Literal,
+        Regex,
+    }
+}
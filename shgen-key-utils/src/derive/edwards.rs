@@ -0,0 +1,310 @@
+//! Fixed-base scalar multiplication and compression of [`LANES`] points at
+//! once, one per lane, over any [`Field`] backend.
+//!
+//! Points use extended twisted Edwards coordinates, and the multiples of the
+//! basepoint are added in affine Niels form, as in curve25519-dalek.
+
+use super::{
+    LANES,
+    field::{self, Field, Limbs},
+};
+
+/// Number of radix-16 digits of a scalar.
+const DIGITS: usize = 64;
+
+/// Multiples 0 to 8 of a point, of which a digit picks one.
+const MULTIPLES: usize = 9;
+
+const BASEPOINT_X: Limbs = [
+    0x6_2d60_8f25_d51a,
+    0x4_12a4_b4f6_592a,
+    0x7_5b71_71a4_b31d,
+    0x1_ff60_5271_18fe,
+    0x2_1693_6d3c_d6e5,
+];
+
+const BASEPOINT_Y: Limbs = [
+    0x6_6666_6666_6658,
+    0x4_cccc_cccc_cccc,
+    0x1_9999_9999_9999,
+    0x3_3333_3333_3333,
+    0x6_6666_6666_6666,
+];
+
+/// 2d, where d = -121665/121666 is the curve constant.
+const EDWARDS_D2: Limbs = [
+    0x6_9b94_26b2_f159,
+    0x3_5050_762a_dd7a,
+    0x3_cf44_c003_8052,
+    0x6_738c_c740_7977,
+    0x2_406d_9dc5_6dff,
+];
+
+/// The identity as (y + x, y - x, 2dxy), for the zero multiple.
+const IDENTITY_NIELS: [Limbs; 3] = [[1, 0, 0, 0, 0], [1, 0, 0, 0, 0], [0; 5]];
+
+#[derive(Clone, Copy)]
+pub struct ExtendedPoint<F> {
+    x: F,
+    y: F,
+    z: F,
+    t: F,
+}
+
+/// A point as ((X:Z), (Y:T)), which additions and doublings produce before
+/// converting back to extended coordinates.
+struct CompletedPoint<F> {
+    x: F,
+    y: F,
+    z: F,
+    t: F,
+}
+
+/// Affine points as (y + x, y - x, 2dxy), stored by coordinate so that a
+/// lane can pick its own point.
+#[derive(Clone, Copy)]
+struct NielsPoints<C, const N: usize> {
+    y_plus_x: [C; N],
+    y_minus_x: [C; N],
+    xy2d: [C; N],
+}
+
+/// Multiples 0 to 8 of 16^(2i) times the basepoint, for i in 0..32.
+pub struct BasepointTable<F: Field>([NielsPoints<F::Constant, MULTIPLES>; DIGITS / 2]);
+
+impl<F: Field> BasepointTable<F> {
+    /// Computes the table with the backend's own arithmetic, so it must only
+    /// be called where the backend can be used.
+    #[must_use]
+    pub fn new() -> Self {
+        let splat = |limbs: &Limbs| F::splat(&F::constant(limbs));
+        let identity = IDENTITY_NIELS.map(|limbs| splat(&limbs));
+
+        let mut rows = Vec::with_capacity(DIGITS / 2);
+        let mut base = (splat(&BASEPOINT_X), splat(&BASEPOINT_Y));
+        for _ in 0..DIGITS / 2 {
+            // Every lane from the step's on adds the base, so that lane i ends
+            // up with i + 1 times it.
+            let base_niels = niels(base.0, base.1);
+            let mut multiples = ExtendedPoint::identity();
+            for step in 0..LANES {
+                let [y_plus_x, y_minus_x, xy2d] = std::array::from_fn(|coordinate| {
+                    identity[coordinate].select(base_niels[coordinate], 0xff << step)
+                });
+                multiples = multiples.add_niels(y_plus_x, y_minus_x, xy2d);
+            }
+
+            let (x, y) = multiples.to_affine();
+            let multiples_niels = niels(x, y);
+            let [y_plus_x, y_minus_x, xy2d] = std::array::from_fn(|coordinate| {
+                let lanes = multiples_niels[coordinate].to_limbs();
+                std::array::from_fn(|multiple| {
+                    F::constant(&match multiple.checked_sub(1) {
+                        Some(lane) => field::canonical(&lanes[lane]),
+                        None => IDENTITY_NIELS[coordinate],
+                    })
+                })
+            });
+            rows.push(NielsPoints {
+                y_plus_x,
+                y_minus_x,
+                xy2d,
+            });
+
+            // The next row's base is 256 times this one's, 32 times the last
+            // multiple.
+            let mut next = multiples;
+            for _ in 0..5 {
+                next = next.double();
+            }
+            let (x, y) = next.to_affine();
+            let last = |coordinate: F| field::canonical(&coordinate.to_limbs()[LANES - 1]);
+            base = (splat(&last(x)), splat(&last(y)));
+        }
+
+        Self(
+            rows.try_into()
+                .unwrap_or_else(|_| unreachable!("a row is computed per pair of digits")),
+        )
+    }
+}
+
+impl<F: Field> ExtendedPoint<F> {
+    #[inline(always)]
+    fn identity() -> Self {
+        Self {
+            x: F::zero(),
+            y: F::one(),
+            z: F::one(),
+            t: F::zero(),
+        }
+    }
+
+    #[inline(always)]
+    fn double(self) -> Self {
+        let xx = self.x.square();
+        let yy = self.y.square();
+        let zz = self.z.square();
+        let x_plus_y_squared = (self.x + self.y).square();
+        let yy_plus_xx = yy + xx;
+        let yy_minus_xx = yy - xx;
+
+        CompletedPoint {
+            x: x_plus_y_squared - yy_plus_xx,
+            y: yy_plus_xx,
+            z: yy_minus_xx,
+            t: zz + zz - yy_minus_xx,
+        }
+        .to_extended()
+    }
+
+    /// Adds to the point in each lane the affine point given in Niels form
+    /// in the same lane.
+    #[inline(always)]
+    fn add_niels(self, y_plus_x: F, y_minus_x: F, xy2d: F) -> Self {
+        let pp = (self.y + self.x) * y_plus_x;
+        let mm = (self.y - self.x) * y_minus_x;
+        let txy2d = self.t * xy2d;
+        let z2 = self.z + self.z;
+
+        CompletedPoint {
+            x: pp - mm,
+            y: pp + mm,
+            z: z2 + txy2d,
+            t: z2 - txy2d,
+        }
+        .to_extended()
+    }
+
+    /// Adds `digits[lane]` times the points of `row` to each lane, without
+    /// branching on the digits.
+    #[inline(always)]
+    fn add_multiple(self, row: &NielsPoints<F::Constant, MULTIPLES>, digits: &[i8; LANES]) -> Self {
+        let indices = digits.map(i8::unsigned_abs);
+        let negative = digits.iter().enumerate().fold(0u8, |mask, (lane, &digit)| {
+            mask | (u8::from(digit < 0) << lane)
+        });
+
+        // -(x, y) = (-x, y), which swaps y + x and y - x and negates 2dxy.
+        let y_plus_x = F::lookup(&row.y_plus_x, &indices);
+        let y_minus_x = F::lookup(&row.y_minus_x, &indices);
+        let xy2d = F::lookup(&row.xy2d, &indices);
+
+        self.add_niels(
+            y_plus_x.select(y_minus_x, negative),
+            y_minus_x.select(y_plus_x, negative),
+            xy2d.select(F::zero() - xy2d, negative),
+        )
+    }
+
+    /// The affine coordinates of the point in each lane.
+    #[inline(always)]
+    fn to_affine(self) -> (F, F) {
+        let z_inverse = self.z.invert();
+        (self.x * z_inverse, self.y * z_inverse)
+    }
+}
+
+impl<F: Field> CompletedPoint<F> {
+    #[inline(always)]
+    fn to_extended(&self) -> ExtendedPoint<F> {
+        ExtendedPoint {
+            x: self.x * self.t,
+            y: self.y * self.z,
+            z: self.z * self.t,
+            t: self.x * self.y,
+        }
+    }
+}
+
+/// The basepoint times each clamped scalar, like
+/// `EdwardsPoint::mul_base_clamped` does for one.
+#[inline(always)]
+pub fn mul_base_clamped<F: Field>(
+    table: &BasepointTable<F>,
+    scalars: &[[u8; 32]; LANES],
+) -> ExtendedPoint<F> {
+    let digits = scalars.map(|scalar| radix_16(&clamp(scalar)));
+    let column = |index: usize| -> [i8; LANES] { digits.map(|digits| digits[index]) };
+
+    // sum(d_i 16^i B) = 16 * sum over odd i + sum over even i, where every
+    // 16^i B for an even i is in the table.
+    let mut point = ExtendedPoint::identity();
+    for (index, row) in (1..DIGITS).step_by(2).zip(&table.0) {
+        point = point.add_multiple(row, &column(index));
+    }
+    for _ in 0..4 {
+        point = point.double();
+    }
+    for (index, row) in (0..DIGITS).step_by(2).zip(&table.0) {
+        point = point.add_multiple(row, &column(index));
+    }
+
+    point
+}
+
+/// The compressed encoding of every point, with a single field inversion
+/// for all of them.
+#[inline(always)]
+pub fn compress_batch<F: Field>(points: &[ExtendedPoint<F>]) -> Vec<[[u8; 32]; LANES]> {
+    // Montgomery's trick: invert the product of every Z, then peel off the
+    // inverse of each Z from the end.
+    let mut products_before = Vec::with_capacity(points.len());
+    let mut product = F::one();
+    for point in points {
+        products_before.push(product);
+        product = product * point.z;
+    }
+
+    let mut inverse = product.invert();
+    let mut compressed = vec![[[0u8; 32]; LANES]; points.len()];
+    for ((point, &product_before), compressed) in points
+        .iter()
+        .zip(&products_before)
+        .zip(&mut compressed)
+        .rev()
+    {
+        let z_inverse = inverse * product_before;
+        inverse = inverse * point.z;
+
+        let xs = (point.x * z_inverse).to_limbs();
+        let ys = (point.y * z_inverse).to_limbs();
+        for ((compressed, x), y) in compressed.iter_mut().zip(&xs).zip(&ys) {
+            *compressed = field::to_bytes(y);
+            compressed[31] |= (field::to_bytes(x)[0] & 1) << 7;
+        }
+    }
+
+    compressed
+}
+
+fn clamp(mut scalar: [u8; 32]) -> [u8; 32] {
+    scalar[0] &= 0b1111_1000;
+    scalar[31] &= 0b0111_1111;
+    scalar[31] |= 0b0100_0000;
+    scalar
+}
+
+/// Signed digits in -8..=8, least significant first. The top bit of the
+/// scalar must be clear, for the last digit to stay below 9.
+fn radix_16(scalar: &[u8; 32]) -> [i8; DIGITS] {
+    let mut digits = [0i8; DIGITS];
+    for (index, byte) in scalar.iter().enumerate() {
+        digits[2 * index] = (byte & 15) as i8;
+        digits[2 * index + 1] = (byte >> 4) as i8;
+    }
+
+    for index in 0..DIGITS - 1 {
+        let carry = (digits[index] + 8) >> 4;
+        digits[index] -= carry << 4;
+        digits[index + 1] += carry;
+    }
+
+    digits
+}
+
+/// The affine points of each lane in Niels form.
+fn niels<F: Field>(x: F, y: F) -> [F; 3] {
+    let d2 = F::splat(&F::constant(&EDWARDS_D2));
+    [y + x, y - x, x * y * d2]
+}
@@ -0,0 +1,497 @@
+//! AVX2 backend, with ten limbs per element alternating between 26 and 25
+//! bits. The lanes are split in two halves of four, with a limb of a half in
+//! one register, so that limb products are single 32-bit multiplies into
+//! 64-bit lanes.
+//!
+//! Values of [`Avx2`] must only be created and used in code compiled with
+//! the `avx2` target feature, on a CPU that has it. Intrinsics are only
+//! called from loops and operators rather than closures, which would be
+//! compiled without the feature and keep the intrinsics out of line.
+
+#[cfg(target_arch = "x86")]
+use std::arch::x86::{
+    __m256i, _mm256_add_epi64, _mm256_and_si256, _mm256_cmpeq_epi64, _mm256_loadu_si256,
+    _mm256_mul_epu32, _mm256_or_si256, _mm256_set1_epi64x, _mm256_setzero_si256, _mm256_srli_epi64,
+    _mm256_storeu_si256, _mm256_sub_epi64, _mm256_xor_si256,
+};
+#[cfg(target_arch = "x86_64")]
+use std::arch::x86_64::{
+    __m256i, _mm256_add_epi64, _mm256_and_si256, _mm256_cmpeq_epi64, _mm256_loadu_si256,
+    _mm256_mul_epu32, _mm256_or_si256, _mm256_set1_epi64x, _mm256_setzero_si256, _mm256_srli_epi64,
+    _mm256_storeu_si256, _mm256_sub_epi64, _mm256_xor_si256,
+};
+use std::ops::{Add, Mul, Sub};
+
+use super::{Field, Limbs};
+use crate::derive::LANES;
+
+const HALF: usize = LANES / 2;
+
+#[derive(Clone, Copy)]
+pub struct Avx2([Half; 2]);
+
+#[derive(Clone, Copy)]
+struct Half([Limb; 10]);
+
+/// A limb of four lanes, each below 2^32 except in the sums that
+/// [`Half::reduce`] carries.
+#[derive(Clone, Copy)]
+struct Limb(__m256i);
+
+/// 4p, added before subtracting so that no limb wraps around.
+const FOUR_P: [u64; 10] = {
+    let mut limbs = [0; 10];
+    let mut index = 0;
+    while index < 10 {
+        limbs[index] = 4 * ((1 << (26 - index % 2)) - 1);
+        index += 1;
+    }
+    limbs[0] -= 4 * 18;
+    limbs
+};
+
+impl Limb {
+    #[inline(always)]
+    fn zero() -> Self {
+        // SAFETY: only called with avx2 enabled, see the module docs.
+        Self(unsafe { _mm256_setzero_si256() })
+    }
+
+    #[inline(always)]
+    fn splat(value: u64) -> Self {
+        // SAFETY: only called with avx2 enabled, see the module docs.
+        Self(unsafe { _mm256_set1_epi64x(value as i64) })
+    }
+
+    #[inline(always)]
+    fn from_lanes(lanes: &[u64; HALF]) -> Self {
+        // SAFETY: only called with avx2 enabled, see the module docs, and
+        // `lanes` holds the 32 bytes that are read.
+        Self(unsafe { _mm256_loadu_si256(lanes.as_ptr().cast()) })
+    }
+
+    #[inline(always)]
+    fn to_lanes(self) -> [u64; HALF] {
+        let mut lanes = [0; HALF];
+        // SAFETY: only called with avx2 enabled, see the module docs, and
+        // `lanes` holds the 32 bytes that are written.
+        unsafe { _mm256_storeu_si256(lanes.as_mut_ptr().cast(), self.0) };
+        lanes
+    }
+
+    #[inline(always)]
+    fn scaled(self, factor: u64) -> Self {
+        self * Self::splat(factor)
+    }
+
+    /// The limb's carry out of its `bits` bits, and the limb without it.
+    #[inline(always)]
+    fn split(self, bits: u32) -> (Self, Self) {
+        let low_bits = Self::splat((1 << bits) - 1);
+        let carry = if bits == 26 {
+            // SAFETY: only called with avx2 enabled, see the module docs.
+            unsafe { _mm256_srli_epi64::<26>(self.0) }
+        } else {
+            // SAFETY: only called with avx2 enabled, see the module docs.
+            unsafe { _mm256_srli_epi64::<25>(self.0) }
+        };
+
+        (Self(carry), self & low_bits)
+    }
+}
+
+impl Add for Limb {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        // SAFETY: only called with avx2 enabled, see the module docs.
+        Self(unsafe { _mm256_add_epi64(self.0, rhs.0) })
+    }
+}
+
+impl Sub for Limb {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        // SAFETY: only called with avx2 enabled, see the module docs.
+        Self(unsafe { _mm256_sub_epi64(self.0, rhs.0) })
+    }
+}
+
+impl Mul for Limb {
+    type Output = Self;
+
+    /// The product of the low 32 bits of each lane.
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        // SAFETY: only called with avx2 enabled, see the module docs.
+        Self(unsafe { _mm256_mul_epu32(self.0, rhs.0) })
+    }
+}
+
+impl std::ops::BitAnd for Limb {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitand(self, rhs: Self) -> Self {
+        // SAFETY: only called with avx2 enabled, see the module docs.
+        Self(unsafe { _mm256_and_si256(self.0, rhs.0) })
+    }
+}
+
+impl std::ops::BitOr for Limb {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitor(self, rhs: Self) -> Self {
+        // SAFETY: only called with avx2 enabled, see the module docs.
+        Self(unsafe { _mm256_or_si256(self.0, rhs.0) })
+    }
+}
+
+impl std::ops::BitXor for Limb {
+    type Output = Self;
+
+    #[inline(always)]
+    fn bitxor(self, rhs: Self) -> Self {
+        // SAFETY: only called with avx2 enabled, see the module docs.
+        Self(unsafe { _mm256_xor_si256(self.0, rhs.0) })
+    }
+}
+
+impl Half {
+    /// Carries the limbs of a sum or product down to their width, with the
+    /// carry out of the top limb wrapping around times 19.
+    #[inline(always)]
+    fn reduce(mut limbs: [Limb; 10]) -> Self {
+        for index in 0..10 {
+            let (carry, low) = limbs[index].split(26 - index as u32 % 2);
+            limbs[index] = low;
+            if index == 9 {
+                limbs[0] = limbs[0] + carry.scaled(19);
+            } else {
+                limbs[index + 1] = limbs[index + 1] + carry;
+            }
+        }
+        let (carry, low) = limbs[0].split(26);
+        limbs[0] = low;
+        limbs[1] = limbs[1] + carry;
+
+        Self(limbs)
+    }
+
+    #[inline(always)]
+    fn add(&self, rhs: &Self) -> Self {
+        let mut sum = self.0;
+        for (limb, rhs) in sum.iter_mut().zip(&rhs.0) {
+            *limb = *limb + *rhs;
+        }
+
+        Self::reduce(sum)
+    }
+
+    #[inline(always)]
+    fn sub(&self, rhs: &Self) -> Self {
+        let mut difference = self.0;
+        for index in 0..10 {
+            difference[index] = difference[index] + Limb::splat(FOUR_P[index]) - rhs.0[index];
+        }
+
+        Self::reduce(difference)
+    }
+
+    #[inline(always)]
+    fn mul(&self, rhs: &Self) -> Self {
+        let (f, g) = (&self.0, &rhs.0);
+        let mut f2 = *f;
+        let mut g19 = *g;
+        for index in 0..10 {
+            f2[index] = f[index] + f[index];
+            g19[index] = g[index].scaled(19);
+        }
+
+        // Limbs at odd positions are each half a bit short of their weight,
+        // so products of two of them count twice, and 2^255 wraps around
+        // to 19.
+        Self::reduce([
+            f[0] * g[0]
+                + f2[1] * g19[9]
+                + f[2] * g19[8]
+                + f2[3] * g19[7]
+                + f[4] * g19[6]
+                + f2[5] * g19[5]
+                + f[6] * g19[4]
+                + f2[7] * g19[3]
+                + f[8] * g19[2]
+                + f2[9] * g19[1],
+            f[0] * g[1]
+                + f[1] * g[0]
+                + f[2] * g19[9]
+                + f[3] * g19[8]
+                + f[4] * g19[7]
+                + f[5] * g19[6]
+                + f[6] * g19[5]
+                + f[7] * g19[4]
+                + f[8] * g19[3]
+                + f[9] * g19[2],
+            f[0] * g[2]
+                + f2[1] * g[1]
+                + f[2] * g[0]
+                + f2[3] * g19[9]
+                + f[4] * g19[8]
+                + f2[5] * g19[7]
+                + f[6] * g19[6]
+                + f2[7] * g19[5]
+                + f[8] * g19[4]
+                + f2[9] * g19[3],
+            f[0] * g[3]
+                + f[1] * g[2]
+                + f[2] * g[1]
+                + f[3] * g[0]
+                + f[4] * g19[9]
+                + f[5] * g19[8]
+                + f[6] * g19[7]
+                + f[7] * g19[6]
+                + f[8] * g19[5]
+                + f[9] * g19[4],
+            f[0] * g[4]
+                + f2[1] * g[3]
+                + f[2] * g[2]
+                + f2[3] * g[1]
+                + f[4] * g[0]
+                + f2[5] * g19[9]
+                + f[6] * g19[8]
+                + f2[7] * g19[7]
+                + f[8] * g19[6]
+                + f2[9] * g19[5],
+            f[0] * g[5]
+                + f[1] * g[4]
+                + f[2] * g[3]
+                + f[3] * g[2]
+                + f[4] * g[1]
+                + f[5] * g[0]
+                + f[6] * g19[9]
+                + f[7] * g19[8]
+                + f[8] * g19[7]
+                + f[9] * g19[6],
+            f[0] * g[6]
+                + f2[1] * g[5]
+                + f[2] * g[4]
+                + f2[3] * g[3]
+                + f[4] * g[2]
+                + f2[5] * g[1]
+                + f[6] * g[0]
+                + f2[7] * g19[9]
+                + f[8] * g19[8]
+                + f2[9] * g19[7],
+            f[0] * g[7]
+                + f[1] * g[6]
+                + f[2] * g[5]
+                + f[3] * g[4]
+                + f[4] * g[3]
+                + f[5] * g[2]
+                + f[6] * g[1]
+                + f[7] * g[0]
+                + f[8] * g19[9]
+                + f[9] * g19[8],
+            f[0] * g[8]
+                + f2[1] * g[7]
+                + f[2] * g[6]
+                + f2[3] * g[5]
+                + f[4] * g[4]
+                + f2[5] * g[3]
+                + f[6] * g[2]
+                + f2[7] * g[1]
+                + f[8] * g[0]
+                + f2[9] * g19[9],
+            f[0] * g[9]
+                + f[1] * g[8]
+                + f[2] * g[7]
+                + f[3] * g[6]
+                + f[4] * g[5]
+                + f[5] * g[4]
+                + f[6] * g[3]
+                + f[7] * g[2]
+                + f[8] * g[1]
+                + f[9] * g[0],
+        ])
+    }
+
+    #[inline(always)]
+    fn square(&self) -> Self {
+        let f = &self.0;
+        let mut f2 = *f;
+        let mut f4 = *f;
+        let mut f19 = *f;
+        for index in 0..10 {
+            f2[index] = f[index] + f[index];
+            f4[index] = f2[index] + f2[index];
+            f19[index] = f[index].scaled(19);
+        }
+
+        // As for multiplying, with the products f_i f_j for i < j counted
+        // twice instead of computed twice.
+        Self::reduce([
+            f[0] * f[0]
+                + f4[1] * f19[9]
+                + f2[2] * f19[8]
+                + f4[3] * f19[7]
+                + f2[4] * f19[6]
+                + f2[5] * f19[5],
+            f2[0] * f[1] + f2[2] * f19[9] + f2[3] * f19[8] + f2[4] * f19[7] + f2[5] * f19[6],
+            f2[0] * f[2]
+                + f2[1] * f[1]
+                + f4[3] * f19[9]
+                + f2[4] * f19[8]
+                + f4[5] * f19[7]
+                + f[6] * f19[6],
+            f2[0] * f[3] + f2[1] * f[2] + f2[4] * f19[9] + f2[5] * f19[8] + f2[6] * f19[7],
+            f2[0] * f[4]
+                + f4[1] * f[3]
+                + f[2] * f[2]
+                + f4[5] * f19[9]
+                + f2[6] * f19[8]
+                + f2[7] * f19[7],
+            f2[0] * f[5] + f2[1] * f[4] + f2[2] * f[3] + f2[6] * f19[9] + f2[7] * f19[8],
+            f2[0] * f[6]
+                + f4[1] * f[5]
+                + f2[2] * f[4]
+                + f2[3] * f[3]
+                + f4[7] * f19[9]
+                + f[8] * f19[8],
+            f2[0] * f[7] + f2[1] * f[6] + f2[2] * f[5] + f2[3] * f[4] + f2[8] * f19[9],
+            f2[0] * f[8]
+                + f4[1] * f[7]
+                + f2[2] * f[6]
+                + f4[3] * f[5]
+                + f[4] * f[4]
+                + f2[9] * f19[9],
+            f2[0] * f[9] + f2[1] * f[8] + f2[2] * f[7] + f2[3] * f[6] + f2[4] * f[5],
+        ])
+    }
+
+    /// The lanes of `other` whose bit is set in the low bits of `mask`.
+    #[inline(always)]
+    fn select(&self, other: &Self, mask: u8) -> Self {
+        let mut lanes = [0u64; HALF];
+        for (lane, bits) in lanes.iter_mut().enumerate() {
+            *bits = u64::from((mask >> lane) & 1).wrapping_neg();
+        }
+        let mask = Limb::from_lanes(&lanes);
+
+        let mut selected = self.0;
+        for (limb, other) in selected.iter_mut().zip(&other.0) {
+            *limb = *limb ^ (mask & (*limb ^ *other));
+        }
+
+        Self(selected)
+    }
+
+    #[inline(always)]
+    fn lookup(constants: &[[u32; 10]], indices: &[u8]) -> Self {
+        let mut lanes = [0u64; HALF];
+        for lane in 0..HALF {
+            lanes[lane] = u64::from(indices[lane]);
+        }
+        let indices = Limb::from_lanes(&lanes);
+
+        let mut selected = [Limb::zero(); 10];
+        for (position, constant) in constants.iter().enumerate() {
+            let position = Limb::splat(position as u64);
+            // SAFETY: only called with avx2 enabled, see the module docs.
+            let mask = Limb(unsafe { _mm256_cmpeq_epi64(indices.0, position.0) });
+            for (limb, &value) in selected.iter_mut().zip(constant) {
+                *limb = *limb | (mask & Limb::splat(u64::from(value)));
+            }
+        }
+
+        Self(selected)
+    }
+}
+
+impl Field for Avx2 {
+    type Constant = [u32; 10];
+
+    #[inline(always)]
+    fn constant(limbs: &Limbs) -> Self::Constant {
+        let mut constant = [0; 10];
+        for (pair, &limb) in constant.chunks_exact_mut(2).zip(limbs) {
+            pair[0] = (limb & ((1 << 26) - 1)) as u32;
+            pair[1] = (limb >> 26) as u32;
+        }
+        constant
+    }
+
+    #[inline(always)]
+    fn splat(constant: &Self::Constant) -> Self {
+        let mut half = Half([Limb::zero(); 10]);
+        for (limb, &value) in half.0.iter_mut().zip(constant) {
+            *limb = Limb::splat(u64::from(value));
+        }
+
+        Self([half; 2])
+    }
+
+    #[inline(always)]
+    fn square(self) -> Self {
+        Self([self.0[0].square(), self.0[1].square()])
+    }
+
+    #[inline(always)]
+    fn select(self, other: Self, mask: u8) -> Self {
+        Self([
+            self.0[0].select(&other.0[0], mask),
+            self.0[1].select(&other.0[1], mask >> HALF),
+        ])
+    }
+
+    #[inline(always)]
+    fn lookup(constants: &[Self::Constant], indices: &[u8; LANES]) -> Self {
+        let (low, high) = indices.split_at(HALF);
+        Self([Half::lookup(constants, low), Half::lookup(constants, high)])
+    }
+
+    #[inline(always)]
+    fn to_limbs(self) -> [Limbs; LANES] {
+        let mut limbs = [[0; 5]; LANES];
+        for (half, lanes) in self.0.iter().zip(limbs.chunks_mut(HALF)) {
+            for (index, pair) in half.0.chunks_exact(2).enumerate() {
+                let low = pair[0].to_lanes();
+                let high = pair[1].to_lanes();
+                for ((limbs, low), high) in lanes.iter_mut().zip(low).zip(high) {
+                    limbs[index] = low + (high << 26);
+                }
+            }
+        }
+        limbs
+    }
+}
+
+impl Add for Avx2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        Self([self.0[0].add(&rhs.0[0]), self.0[1].add(&rhs.0[1])])
+    }
+}
+
+impl Sub for Avx2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        Self([self.0[0].sub(&rhs.0[0]), self.0[1].sub(&rhs.0[1])])
+    }
+}
+
+impl Mul for Avx2 {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        Self([self.0[0].mul(&rhs.0[0]), self.0[1].mul(&rhs.0[1])])
+    }
+}
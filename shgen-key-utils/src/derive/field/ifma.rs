@@ -0,0 +1,438 @@
+//! AVX-512 IFMA backend, with five limbs per element in radix 2^51 and the
+//! eight lanes in one register per limb. The 52-bit multiply-accumulates
+//! leave every limb product split at bit 52, so high halves count twice.
+//!
+//! The multiplication and squaring follow the 4-lane IFMA backend of
+//! curve25519-dalek.
+//!
+//! Values of [`Ifma`] must only be created and used in code compiled with
+//! the `avx512f` and `avx512ifma` target features, on a CPU that has them.
+//! Intrinsics are only called from loops rather than closures, which would
+//! be compiled without those features and keep the intrinsics out of line.
+
+use std::{
+    arch::x86_64::{
+        __m512i, _mm512_add_epi64, _mm512_and_si512, _mm512_cmpeq_epu64_mask, _mm512_loadu_si512,
+        _mm512_madd52hi_epu64, _mm512_madd52lo_epu64, _mm512_mask_mov_epi64, _mm512_set1_epi64,
+        _mm512_setzero_si512, _mm512_slli_epi64, _mm512_srli_epi64, _mm512_storeu_si512,
+        _mm512_sub_epi64,
+    },
+    ops::{Add, Mul, Sub},
+};
+
+use super::{Field, LOW_51_BITS, Limbs};
+use crate::derive::LANES;
+
+#[derive(Clone, Copy)]
+pub struct Ifma([__m512i; 5]);
+
+#[inline(always)]
+fn splat(value: u64) -> __m512i {
+    // SAFETY: only called with avx512f enabled, see the module docs.
+    unsafe { _mm512_set1_epi64(value as i64) }
+}
+
+#[inline(always)]
+fn add(x: __m512i, y: __m512i) -> __m512i {
+    // SAFETY: only called with avx512f enabled, see the module docs.
+    unsafe { _mm512_add_epi64(x, y) }
+}
+
+#[inline(always)]
+fn madd52lo(z: __m512i, x: __m512i, y: __m512i) -> __m512i {
+    // SAFETY: only called with avx512ifma enabled, see the module docs.
+    unsafe { _mm512_madd52lo_epu64(z, x, y) }
+}
+
+#[inline(always)]
+fn madd52hi(z: __m512i, x: __m512i, y: __m512i) -> __m512i {
+    // SAFETY: only called with avx512ifma enabled, see the module docs.
+    unsafe { _mm512_madd52hi_epu64(z, x, y) }
+}
+
+#[inline(always)]
+fn shl<const BITS: u32>(x: __m512i) -> __m512i {
+    // SAFETY: only called with avx512f enabled, see the module docs.
+    unsafe { _mm512_slli_epi64::<BITS>(x) }
+}
+
+#[inline(always)]
+fn shr<const BITS: u32>(x: __m512i) -> __m512i {
+    // SAFETY: only called with avx512f enabled, see the module docs.
+    unsafe { _mm512_srli_epi64::<BITS>(x) }
+}
+
+#[inline(always)]
+fn zero() -> __m512i {
+    // SAFETY: only called with avx512f enabled, see the module docs.
+    unsafe { _mm512_setzero_si512() }
+}
+
+impl Ifma {
+    /// Carries every limb down to 51 bits, plus the small carry it receives.
+    #[inline(always)]
+    fn reduce(limbs: [__m512i; 5]) -> Self {
+        let low_bits = splat(LOW_51_BITS);
+        let mut carries = [zero(); 5];
+        let mut reduced = [zero(); 5];
+        for index in 0..5 {
+            carries[index] = shr::<51>(limbs[index]);
+            // SAFETY: only called with avx512f enabled, see the module docs.
+            reduced[index] = unsafe { _mm512_and_si512(limbs[index], low_bits) };
+        }
+
+        reduced[0] = madd52lo(reduced[0], carries[4], splat(19));
+        for index in 1..5 {
+            reduced[index] = add(reduced[index], carries[index - 1]);
+        }
+
+        Self(reduced)
+    }
+
+    /// Sums the accumulators of products at weight 2^(51 i), and of high
+    /// halves counting twice, then reduces the result.
+    #[inline(always)]
+    fn combine(single: [__m512i; 5], double: [__m512i; 5]) -> Self {
+        let mut sum = single;
+        for index in 0..5 {
+            sum[index] = add(sum[index], add(double[index], double[index]));
+        }
+
+        Self::reduce(sum)
+    }
+}
+
+impl Field for Ifma {
+    type Constant = Limbs;
+
+    #[inline(always)]
+    fn constant(limbs: &Limbs) -> Self::Constant {
+        *limbs
+    }
+
+    #[inline(always)]
+    fn splat(constant: &Self::Constant) -> Self {
+        let mut limbs = [zero(); 5];
+        for index in 0..5 {
+            limbs[index] = splat(constant[index]);
+        }
+
+        Self(limbs)
+    }
+
+    #[inline(always)]
+    fn square(self) -> Self {
+        let x = &self.0;
+
+        let mut z0_2 = zero();
+        let mut z1_2 = zero();
+        let mut z2_2 = zero();
+        let mut z3_2 = zero();
+        let mut z4_2 = zero();
+        let mut z5_2 = zero();
+        let mut z6_2 = zero();
+        let mut z7_2 = zero();
+        let mut z9_2 = zero();
+
+        let mut z2_4 = zero();
+        let mut z3_4 = zero();
+        let mut z4_4 = zero();
+        let mut z5_4 = zero();
+        let mut z6_4 = zero();
+        let mut z7_4 = zero();
+        let mut z8_4 = zero();
+
+        let mut z0_1 = madd52lo(zero(), x[0], x[0]);
+
+        let mut z1_1 = zero();
+        z1_2 = madd52lo(z1_2, x[0], x[1]);
+        z1_2 = madd52hi(z1_2, x[0], x[0]);
+
+        z2_4 = madd52hi(z2_4, x[0], x[1]);
+        let mut z2_1 = shl::<2>(z2_4);
+        z2_2 = madd52lo(z2_2, x[0], x[2]);
+        z2_1 = madd52lo(z2_1, x[1], x[1]);
+
+        z3_4 = madd52hi(z3_4, x[0], x[2]);
+        let mut z3_1 = shl::<2>(z3_4);
+        z3_2 = madd52lo(z3_2, x[1], x[2]);
+        z3_2 = madd52lo(z3_2, x[0], x[3]);
+        z3_2 = madd52hi(z3_2, x[1], x[1]);
+
+        z4_4 = madd52hi(z4_4, x[1], x[2]);
+        z4_4 = madd52hi(z4_4, x[0], x[3]);
+        let mut z4_1 = shl::<2>(z4_4);
+        z4_2 = madd52lo(z4_2, x[1], x[3]);
+        z4_2 = madd52lo(z4_2, x[0], x[4]);
+        z4_1 = madd52lo(z4_1, x[2], x[2]);
+
+        z5_4 = madd52hi(z5_4, x[1], x[3]);
+        z5_4 = madd52hi(z5_4, x[0], x[4]);
+        let mut z5_1 = shl::<2>(z5_4);
+        z5_2 = madd52lo(z5_2, x[2], x[3]);
+        z5_2 = madd52lo(z5_2, x[1], x[4]);
+        z5_2 = madd52hi(z5_2, x[2], x[2]);
+
+        z6_4 = madd52hi(z6_4, x[2], x[3]);
+        z6_4 = madd52hi(z6_4, x[1], x[4]);
+        let mut z6_1 = shl::<2>(z6_4);
+        z6_2 = madd52lo(z6_2, x[2], x[4]);
+        z6_1 = madd52lo(z6_1, x[3], x[3]);
+
+        z7_4 = madd52hi(z7_4, x[2], x[4]);
+        let mut z7_1 = shl::<2>(z7_4);
+        z7_2 = madd52lo(z7_2, x[3], x[4]);
+        z7_2 = madd52hi(z7_2, x[3], x[3]);
+
+        z8_4 = madd52hi(z8_4, x[3], x[4]);
+        let mut z8_1 = shl::<2>(z8_4);
+        z8_1 = madd52lo(z8_1, x[4], x[4]);
+
+        let mut z9_1 = zero();
+        z9_2 = madd52hi(z9_2, x[4], x[4]);
+
+        z5_1 = add(z5_1, shl::<1>(z5_2));
+        z6_1 = add(z6_1, shl::<1>(z6_2));
+        z7_1 = add(z7_1, shl::<1>(z7_2));
+        z9_1 = add(z9_1, shl::<1>(z9_2));
+
+        // Fold the limbs above 2^255 back in, times 19.
+        let r19 = splat(19);
+        let t0 = madd52hi(zero(), r19, z9_1);
+        let t1 = madd52lo(zero(), r19, shr::<52>(z9_1));
+
+        z4_2 = madd52lo(z4_2, r19, shr::<52>(z8_1));
+        z3_2 = madd52lo(z3_2, r19, shr::<52>(z7_1));
+        z2_2 = madd52lo(z2_2, r19, shr::<52>(z6_1));
+        z1_2 = madd52lo(z1_2, r19, shr::<52>(z5_1));
+
+        z0_2 = madd52lo(z0_2, r19, add(t0, t1));
+        z1_2 = madd52hi(z1_2, r19, z5_1);
+        z2_2 = madd52hi(z2_2, r19, z6_1);
+        z3_2 = madd52hi(z3_2, r19, z7_1);
+        z4_2 = madd52hi(z4_2, r19, z8_1);
+
+        z0_1 = madd52lo(z0_1, r19, z5_1);
+        z1_1 = madd52lo(z1_1, r19, z6_1);
+        z2_1 = madd52lo(z2_1, r19, z7_1);
+        z3_1 = madd52lo(z3_1, r19, z8_1);
+        z4_1 = madd52lo(z4_1, r19, z9_1);
+
+        Self::combine(
+            [z0_1, z1_1, z2_1, z3_1, z4_1],
+            [z0_2, z1_2, z2_2, z3_2, z4_2],
+        )
+    }
+
+    #[inline(always)]
+    fn select(self, other: Self, mask: u8) -> Self {
+        let mut selected = self.0;
+        for (limb, other) in selected.iter_mut().zip(&other.0) {
+            // SAFETY: only called with avx512f enabled, see the module docs.
+            *limb = unsafe { _mm512_mask_mov_epi64(*limb, mask, *other) };
+        }
+
+        Self(selected)
+    }
+
+    #[inline(always)]
+    fn lookup(constants: &[Self::Constant], indices: &[u8; LANES]) -> Self {
+        let mut wide_indices = [0u64; LANES];
+        for lane in 0..LANES {
+            wide_indices[lane] = u64::from(indices[lane]);
+        }
+        // SAFETY: only called with avx512f enabled, see the module docs, and
+        // `wide_indices` holds the 64 bytes that are read.
+        let indices = unsafe { _mm512_loadu_si512(wide_indices.as_ptr().cast()) };
+
+        let mut selected = [zero(); 5];
+        for (position, constant) in constants.iter().enumerate() {
+            // SAFETY: only called with avx512f enabled, see the module docs.
+            unsafe {
+                let mask = _mm512_cmpeq_epu64_mask(indices, splat(position as u64));
+                for index in 0..5 {
+                    selected[index] =
+                        _mm512_mask_mov_epi64(selected[index], mask, splat(constant[index]));
+                }
+            }
+        }
+
+        Self(selected)
+    }
+
+    #[inline(always)]
+    fn to_limbs(self) -> [Limbs; LANES] {
+        let mut lanes = [[0u64; LANES]; 5];
+        for (row, limb) in lanes.iter_mut().zip(self.0) {
+            // SAFETY: only called with avx512f enabled, see the module docs,
+            // and `row` holds the 64 bytes that are written.
+            unsafe { _mm512_storeu_si512(row.as_mut_ptr().cast(), limb) };
+        }
+
+        let mut limbs = [[0u64; 5]; LANES];
+        for lane in 0..LANES {
+            for index in 0..5 {
+                limbs[lane][index] = lanes[index][lane];
+            }
+        }
+
+        limbs
+    }
+}
+
+impl Add for Ifma {
+    type Output = Self;
+
+    #[inline(always)]
+    fn add(self, rhs: Self) -> Self {
+        let mut sum = self.0;
+        for (limb, rhs) in sum.iter_mut().zip(rhs.0) {
+            *limb = add(*limb, rhs);
+        }
+
+        Self::reduce(sum)
+    }
+}
+
+impl Sub for Ifma {
+    type Output = Self;
+
+    #[inline(always)]
+    fn sub(self, rhs: Self) -> Self {
+        // 16p, added before subtracting so that no limb wraps around.
+        let sixteen_p = [splat(16 * (LOW_51_BITS - 18)), splat(16 * LOW_51_BITS)];
+
+        let mut difference = self.0;
+        for index in 0..5 {
+            let bias = sixteen_p[usize::from(index > 0)];
+            // SAFETY: only called with avx512f enabled, see the module docs.
+            difference[index] =
+                unsafe { _mm512_sub_epi64(add(difference[index], bias), rhs.0[index]) };
+        }
+
+        Self::reduce(difference)
+    }
+}
+
+impl Mul for Ifma {
+    type Output = Self;
+
+    #[inline(always)]
+    fn mul(self, rhs: Self) -> Self {
+        let x = &self.0;
+        let y = &rhs.0;
+
+        let mut z0_1 = zero();
+        let mut z1_1 = zero();
+        let mut z2_1 = zero();
+        let mut z3_1 = zero();
+        let mut z4_1 = zero();
+        let mut z5_1 = zero();
+        let mut z6_1 = zero();
+        let mut z7_1 = zero();
+        let mut z8_1 = zero();
+
+        let mut z0_2 = zero();
+        let mut z1_2 = zero();
+        let mut z2_2 = zero();
+        let mut z3_2 = zero();
+        let mut z4_2 = zero();
+        let mut z5_2 = zero();
+        let mut z6_2 = zero();
+        let mut z7_2 = zero();
+        let mut z8_2 = zero();
+        let mut z9_2 = zero();
+
+        // Grouped in waves of independent multiply-accumulates, to keep the
+        // pipeline full.
+        z4_1 = madd52lo(z4_1, x[2], y[2]);
+        z5_2 = madd52hi(z5_2, x[2], y[2]);
+        z5_1 = madd52lo(z5_1, x[4], y[1]);
+        z6_2 = madd52hi(z6_2, x[4], y[1]);
+        z6_1 = madd52lo(z6_1, x[4], y[2]);
+        z7_2 = madd52hi(z7_2, x[4], y[2]);
+        z7_1 = madd52lo(z7_1, x[4], y[3]);
+        z8_2 = madd52hi(z8_2, x[4], y[3]);
+
+        z4_1 = madd52lo(z4_1, x[3], y[1]);
+        z5_2 = madd52hi(z5_2, x[3], y[1]);
+        z5_1 = madd52lo(z5_1, x[3], y[2]);
+        z6_2 = madd52hi(z6_2, x[3], y[2]);
+        z6_1 = madd52lo(z6_1, x[3], y[3]);
+        z7_2 = madd52hi(z7_2, x[3], y[3]);
+        z7_1 = madd52lo(z7_1, x[3], y[4]);
+        z8_2 = madd52hi(z8_2, x[3], y[4]);
+
+        z8_1 = madd52lo(z8_1, x[4], y[4]);
+        z9_2 = madd52hi(z9_2, x[4], y[4]);
+        z4_1 = madd52lo(z4_1, x[4], y[0]);
+        z5_2 = madd52hi(z5_2, x[4], y[0]);
+        z5_1 = madd52lo(z5_1, x[2], y[3]);
+        z6_2 = madd52hi(z6_2, x[2], y[3]);
+        z6_1 = madd52lo(z6_1, x[2], y[4]);
+        z7_2 = madd52hi(z7_2, x[2], y[4]);
+
+        let z8 = add(z8_1, add(z8_2, z8_2));
+        let z9 = add(z9_2, z9_2);
+
+        z3_1 = madd52lo(z3_1, x[3], y[0]);
+        z4_2 = madd52hi(z4_2, x[3], y[0]);
+        z4_1 = madd52lo(z4_1, x[1], y[3]);
+        z5_2 = madd52hi(z5_2, x[1], y[3]);
+        z5_1 = madd52lo(z5_1, x[1], y[4]);
+        z6_2 = madd52hi(z6_2, x[1], y[4]);
+        z2_1 = madd52lo(z2_1, x[2], y[0]);
+        z3_2 = madd52hi(z3_2, x[2], y[0]);
+
+        let z6 = add(z6_1, add(z6_2, z6_2));
+        let z7 = add(z7_1, add(z7_2, z7_2));
+
+        z3_1 = madd52lo(z3_1, x[2], y[1]);
+        z4_2 = madd52hi(z4_2, x[2], y[1]);
+        z4_1 = madd52lo(z4_1, x[0], y[4]);
+        z5_2 = madd52hi(z5_2, x[0], y[4]);
+        z1_1 = madd52lo(z1_1, x[1], y[0]);
+        z2_2 = madd52hi(z2_2, x[1], y[0]);
+        z2_1 = madd52lo(z2_1, x[1], y[1]);
+        z3_2 = madd52hi(z3_2, x[1], y[1]);
+
+        let z5 = add(z5_1, add(z5_2, z5_2));
+
+        z3_1 = madd52lo(z3_1, x[1], y[2]);
+        z4_2 = madd52hi(z4_2, x[1], y[2]);
+        z0_1 = madd52lo(z0_1, x[0], y[0]);
+        z1_2 = madd52hi(z1_2, x[0], y[0]);
+        z1_1 = madd52lo(z1_1, x[0], y[1]);
+        z2_1 = madd52lo(z2_1, x[0], y[2]);
+        z2_2 = madd52hi(z2_2, x[0], y[1]);
+        z3_2 = madd52hi(z3_2, x[0], y[2]);
+
+        // Fold the limbs above 2^255 back in, times 19.
+        let r19 = splat(19);
+        let t0 = madd52hi(zero(), r19, z9);
+        let t1 = madd52lo(zero(), r19, shr::<52>(z9));
+        z3_1 = madd52lo(z3_1, x[0], y[3]);
+        z4_2 = madd52hi(z4_2, x[0], y[3]);
+        z1_2 = madd52lo(z1_2, r19, shr::<52>(z5));
+        z2_2 = madd52lo(z2_2, r19, shr::<52>(z6));
+        z3_2 = madd52lo(z3_2, r19, shr::<52>(z7));
+        z0_1 = madd52lo(z0_1, r19, z5);
+
+        z4_1 = madd52lo(z4_1, r19, z9);
+        z1_1 = madd52lo(z1_1, r19, z6);
+        z0_2 = madd52lo(z0_2, r19, add(t0, t1));
+        z4_2 = madd52hi(z4_2, r19, z8);
+        z2_1 = madd52lo(z2_1, r19, z7);
+        z1_2 = madd52hi(z1_2, r19, z5);
+        z2_2 = madd52hi(z2_2, r19, z6);
+        z3_2 = madd52hi(z3_2, r19, z7);
+
+        z3_1 = madd52lo(z3_1, r19, z8);
+        z4_2 = madd52lo(z4_2, r19, shr::<52>(z8));
+
+        Self::combine(
+            [z0_1, z1_1, z2_1, z3_1, z4_1],
+            [z0_2, z1_2, z2_2, z3_2, z4_2],
+        )
+    }
+}
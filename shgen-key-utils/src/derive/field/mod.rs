@@ -0,0 +1,134 @@
+//! Arithmetic modulo p = 2^255 - 19 on [`LANES`] field elements at once, one
+//! per lane, for the curve code in [`super::edwards`].
+//!
+//! Every operation returns limbs small enough to be used as the input of any
+//! other, so that the curve formulas don't have to track bounds.
+
+use std::ops::{Add, Mul, Sub};
+
+use super::LANES;
+
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+pub mod avx2;
+#[cfg(target_arch = "x86_64")]
+pub mod ifma;
+
+/// A field element in radix 2^51, the form constants are written in.
+pub type Limbs = [u64; 5];
+
+const LOW_51_BITS: u64 = (1 << 51) - 1;
+
+pub trait Field: Copy + Add<Output = Self> + Sub<Output = Self> + Mul<Output = Self> {
+    /// A single field element in the backend's own representation, ready to
+    /// be copied to every lane.
+    type Constant: Copy;
+
+    fn constant(limbs: &Limbs) -> Self::Constant;
+
+    fn splat(constant: &Self::Constant) -> Self;
+
+    fn square(self) -> Self;
+
+    /// The lanes of `other` whose bit is set in `mask`, and of `self` for
+    /// the others.
+    fn select(self, other: Self, mask: u8) -> Self;
+
+    /// The constant at each lane's index. Every constant is read, so that
+    /// the time taken doesn't depend on the indices.
+    fn lookup(constants: &[Self::Constant], indices: &[u8; LANES]) -> Self;
+
+    /// Each lane in radix 2^51, with limbs that may not be reduced.
+    fn to_limbs(self) -> [Limbs; LANES];
+
+    #[inline(always)]
+    fn zero() -> Self {
+        Self::splat(&Self::constant(&[0; 5]))
+    }
+
+    #[inline(always)]
+    fn one() -> Self {
+        Self::splat(&Self::constant(&[1, 0, 0, 0, 0]))
+    }
+
+    #[inline(always)]
+    fn square_times(self, times: u32) -> Self {
+        let mut x = self;
+        for _ in 0..times {
+            x = x.square();
+        }
+        x
+    }
+
+    /// The inverse of every lane, as x^(p - 2). Zero is mapped to zero.
+    #[inline(always)]
+    fn invert(self) -> Self {
+        let z2 = self.square();
+        let z9 = z2.square_times(2) * self;
+        let z11 = z2 * z9;
+        let z_5_0 = z11.square() * z9;
+        let z_10_0 = z_5_0.square_times(5) * z_5_0;
+        let z_20_0 = z_10_0.square_times(10) * z_10_0;
+        let z_40_0 = z_20_0.square_times(20) * z_20_0;
+        let z_50_0 = z_40_0.square_times(10) * z_10_0;
+        let z_100_0 = z_50_0.square_times(50) * z_50_0;
+        let z_200_0 = z_100_0.square_times(100) * z_100_0;
+        let z_250_0 = z_200_0.square_times(50) * z_50_0;
+
+        // (2^250 - 1) * 2^5 + 11 = 2^255 - 21 = p - 2
+        z_250_0.square_times(5) * z11
+    }
+}
+
+/// The unique representative of `limbs` below p, in radix 2^51.
+pub fn canonical(limbs: &Limbs) -> Limbs {
+    let mut limbs = carry(limbs);
+
+    // Whether the value is at least p, which adding 19 carries out of bit 255.
+    let overflow = limbs.iter().fold(19, |carry, &limb| (limb + carry) >> 51);
+
+    limbs[0] += 19 * overflow;
+    for index in 0..4 {
+        limbs[index + 1] += limbs[index] >> 51;
+        limbs[index] &= LOW_51_BITS;
+    }
+    limbs[4] &= LOW_51_BITS;
+
+    limbs
+}
+
+/// The little-endian encoding of `limbs`, reduced below p.
+pub fn to_bytes(limbs: &Limbs) -> [u8; 32] {
+    let mut bytes = [0u8; 32];
+    let mut pending = 0u128;
+    let mut pending_bits = 0;
+    let mut written = 0;
+
+    for limb in canonical(limbs) {
+        pending |= u128::from(limb) << pending_bits;
+        pending_bits += 51;
+
+        while pending_bits >= 8 && written < bytes.len() {
+            bytes[written] = pending as u8;
+            written += 1;
+            pending >>= 8;
+            pending_bits -= 8;
+        }
+    }
+    bytes[written] = pending as u8;
+
+    bytes
+}
+
+/// Moves every limb's bits above 51 to the next limb, bringing any u64
+/// limbs below 2^52.
+fn carry(limbs: &Limbs) -> Limbs {
+    let carries = limbs.map(|limb| limb >> 51);
+    let mut limbs = limbs.map(|limb| limb & LOW_51_BITS);
+
+    limbs[0] += 19 * carries[4];
+    for index in 1..5 {
+        limbs[index] += carries[index - 1];
+    }
+
+    limbs
+}
@@ -0,0 +1,389 @@
+//! Batched derivation of Ed25519 public keys from secret keys (seeds).
+//!
+//! Deriving a public key hashes the seed with SHA-512 and multiplies the
+//! basepoint by the clamped hash. Doing it for a batch at once lets the
+//! seeds be hashed and multiplied side by side in vector registers, and the
+//! points be compressed with a single field inversion.
+//!
+//! The derivation is compiled once per [`SimdLevel`]: with AVX-512 IFMA or
+//! AVX2 vectors for the curve arithmetic, or with curve25519-dalek's serial
+//! arithmetic for the baseline.
+
+mod edwards;
+mod field;
+
+use std::sync::OnceLock;
+
+use curve25519_dalek::EdwardsPoint;
+use ed25519_dalek::{PUBLIC_KEY_LENGTH, SECRET_KEY_LENGTH};
+
+use self::{edwards::BasepointTable, field::Field};
+use crate::simd::SimdLevel;
+
+/// Number of keys derived side by side.
+pub const LANES: usize = 8;
+
+/// Derives the public key of every secret key, like
+/// `SigningKey::from_bytes(secret_key).verifying_key()` does for one.
+///
+/// # Panics
+///
+/// If the slices don't have the same length.
+pub fn public_keys(
+    secret_keys: &[[u8; SECRET_KEY_LENGTH]],
+    public_keys: &mut [[u8; PUBLIC_KEY_LENGTH]],
+) {
+    assert_eq!(secret_keys.len(), public_keys.len());
+
+    match SimdLevel::detected() {
+        #[cfg(target_arch = "x86_64")]
+        // SAFETY: the CPU supports the enabled target features.
+        SimdLevel::Avx512 => unsafe { public_keys_avx512(secret_keys, public_keys) },
+        #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+        // SAFETY: the CPU supports the enabled target features.
+        SimdLevel::Avx2 => unsafe { public_keys_avx2(secret_keys, public_keys) },
+        _ => public_keys_baseline(secret_keys, public_keys),
+    }
+}
+
+#[cfg(target_arch = "x86_64")]
+#[target_feature(enable = "avx512f,avx512vl,avx512ifma")]
+fn public_keys_avx512(
+    secret_keys: &[[u8; SECRET_KEY_LENGTH]],
+    public_keys: &mut [[u8; PUBLIC_KEY_LENGTH]],
+) {
+    static TABLE: OnceLock<BasepointTable<field::ifma::Ifma>> = OnceLock::new();
+    derive_lanes(
+        TABLE.get_or_init(BasepointTable::new),
+        secret_keys,
+        public_keys,
+    );
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+#[target_feature(enable = "avx2")]
+fn public_keys_avx2(
+    secret_keys: &[[u8; SECRET_KEY_LENGTH]],
+    public_keys: &mut [[u8; PUBLIC_KEY_LENGTH]],
+) {
+    static TABLE: OnceLock<BasepointTable<field::avx2::Avx2>> = OnceLock::new();
+    derive_lanes(
+        TABLE.get_or_init(BasepointTable::new),
+        secret_keys,
+        public_keys,
+    );
+}
+
+/// Without vector extensions, curve25519-dalek's 64-bit serial arithmetic
+/// is faster than spreading lanes over 128-bit registers.
+fn public_keys_baseline(
+    secret_keys: &[[u8; SECRET_KEY_LENGTH]],
+    public_keys: &mut [[u8; PUBLIC_KEY_LENGTH]],
+) {
+    let mut points = Vec::with_capacity(secret_keys.len());
+    for chunk in secret_keys.chunks(LANES) {
+        let scalars = sha512::hash_lanes(&padded(chunk));
+        points.extend(
+            scalars[..chunk.len()]
+                .iter()
+                .map(|&scalar| EdwardsPoint::mul_base_clamped(scalar)),
+        );
+    }
+
+    for (public_key, compressed) in public_keys
+        .iter_mut()
+        .zip(EdwardsPoint::compress_batch(&points))
+    {
+        *public_key = compressed.to_bytes();
+    }
+}
+
+#[inline(always)]
+fn derive_lanes<F: Field>(
+    table: &BasepointTable<F>,
+    secret_keys: &[[u8; SECRET_KEY_LENGTH]],
+    public_keys: &mut [[u8; PUBLIC_KEY_LENGTH]],
+) {
+    // A loop rather than a closure, which would be compiled without the
+    // caller's target features.
+    let mut points = Vec::with_capacity(secret_keys.len().div_ceil(LANES));
+    for chunk in secret_keys.chunks(LANES) {
+        let scalars = sha512::hash_lanes(&padded(chunk));
+        points.push(edwards::mul_base_clamped(table, &scalars));
+    }
+
+    for (public_key, compressed) in public_keys
+        .iter_mut()
+        .zip(edwards::compress_batch(&points).iter().flatten())
+    {
+        *public_key = *compressed;
+    }
+}
+
+/// A chunk of up to [`LANES`] seeds, with zero seeds in the missing lanes.
+fn padded(chunk: &[[u8; SECRET_KEY_LENGTH]]) -> [[u8; SECRET_KEY_LENGTH]; LANES] {
+    let mut seeds = [[0u8; SECRET_KEY_LENGTH]; LANES];
+    seeds[..chunk.len()].copy_from_slice(chunk);
+    seeds
+}
+
+/// SHA-512 of [`LANES`] seeds at once, written lane by lane so that the
+/// compiler can keep every lane of a word in one vector register.
+mod sha512 {
+    use super::LANES;
+    use ed25519_dalek::SECRET_KEY_LENGTH;
+
+    type Word = [u64; LANES];
+
+    const INITIAL_STATE: [u64; 8] = [
+        0x6a09_e667_f3bc_c908,
+        0xbb67_ae85_84ca_a73b,
+        0x3c6e_f372_fe94_f82b,
+        0xa54f_f53a_5f1d_36f1,
+        0x510e_527f_ade6_82d1,
+        0x9b05_688c_2b3e_6c1f,
+        0x1f83_d9ab_fb41_bd6b,
+        0x5be0_cd19_137e_2179,
+    ];
+
+    const ROUND_CONSTANTS: [u64; 80] = [
+        0x428a_2f98_d728_ae22,
+        0x7137_4491_23ef_65cd,
+        0xb5c0_fbcf_ec4d_3b2f,
+        0xe9b5_dba5_8189_dbbc,
+        0x3956_c25b_f348_b538,
+        0x59f1_11f1_b605_d019,
+        0x923f_82a4_af19_4f9b,
+        0xab1c_5ed5_da6d_8118,
+        0xd807_aa98_a303_0242,
+        0x1283_5b01_4570_6fbe,
+        0x2431_85be_4ee4_b28c,
+        0x550c_7dc3_d5ff_b4e2,
+        0x72be_5d74_f27b_896f,
+        0x80de_b1fe_3b16_96b1,
+        0x9bdc_06a7_25c7_1235,
+        0xc19b_f174_cf69_2694,
+        0xe49b_69c1_9ef1_4ad2,
+        0xefbe_4786_384f_25e3,
+        0x0fc1_9dc6_8b8c_d5b5,
+        0x240c_a1cc_77ac_9c65,
+        0x2de9_2c6f_592b_0275,
+        0x4a74_84aa_6ea6_e483,
+        0x5cb0_a9dc_bd41_fbd4,
+        0x76f9_88da_8311_53b5,
+        0x983e_5152_ee66_dfab,
+        0xa831_c66d_2db4_3210,
+        0xb003_27c8_98fb_213f,
+        0xbf59_7fc7_beef_0ee4,
+        0xc6e0_0bf3_3da8_8fc2,
+        0xd5a7_9147_930a_a725,
+        0x06ca_6351_e003_826f,
+        0x1429_2967_0a0e_6e70,
+        0x27b7_0a85_46d2_2ffc,
+        0x2e1b_2138_5c26_c926,
+        0x4d2c_6dfc_5ac4_2aed,
+        0x5338_0d13_9d95_b3df,
+        0x650a_7354_8baf_63de,
+        0x766a_0abb_3c77_b2a8,
+        0x81c2_c92e_47ed_aee6,
+        0x9272_2c85_1482_353b,
+        0xa2bf_e8a1_4cf1_0364,
+        0xa81a_664b_bc42_3001,
+        0xc24b_8b70_d0f8_9791,
+        0xc76c_51a3_0654_be30,
+        0xd192_e819_d6ef_5218,
+        0xd699_0624_5565_a910,
+        0xf40e_3585_5771_202a,
+        0x106a_a070_32bb_d1b8,
+        0x19a4_c116_b8d2_d0c8,
+        0x1e37_6c08_5141_ab53,
+        0x2748_774c_df8e_eb99,
+        0x34b0_bcb5_e19b_48a8,
+        0x391c_0cb3_c5c9_5a63,
+        0x4ed8_aa4a_e341_8acb,
+        0x5b9c_ca4f_7763_e373,
+        0x682e_6ff3_d6b2_b8a3,
+        0x748f_82ee_5def_b2fc,
+        0x78a5_636f_4317_2f60,
+        0x84c8_7814_a1f0_ab72,
+        0x8cc7_0208_1a64_39ec,
+        0x90be_fffa_2363_1e28,
+        0xa450_6ceb_de82_bde9,
+        0xbef9_a3f7_b2c6_7915,
+        0xc671_78f2_e372_532b,
+        0xca27_3ece_ea26_619c,
+        0xd186_b8c7_21c0_c207,
+        0xeada_7dd6_cde0_eb1e,
+        0xf57d_4f7f_ee6e_d178,
+        0x06f0_67aa_7217_6fba,
+        0x0a63_7dc5_a2c8_98a6,
+        0x113f_9804_bef9_0dae,
+        0x1b71_0b35_131c_471b,
+        0x28db_77f5_2304_7d84,
+        0x32ca_ab7b_40c7_2493,
+        0x3c9e_be0a_15c9_bebc,
+        0x431d_67c4_9c10_0d4c,
+        0x4cc5_d4be_cb3e_42b6,
+        0x597f_299c_fc65_7e2a,
+        0x5fcb_6fab_3ad6_faec,
+        0x6c44_198c_4a47_5817,
+    ];
+
+    /// Hashes every seed, returning the first half of each digest, which is
+    /// all that the secret scalar is made of.
+    #[inline(always)]
+    pub fn hash_lanes(seeds: &[[u8; SECRET_KEY_LENGTH]; LANES]) -> [[u8; 32]; LANES] {
+        // A 32-byte seed always fits in one padded block: the seed, a one
+        // bit, zeros, and the message length in bits.
+        let mut schedule = [[0u64; LANES]; 80];
+        for (lane, seed) in seeds.iter().enumerate() {
+            let (words, _) = seed.as_chunks::<8>();
+            for (index, word) in words.iter().enumerate() {
+                schedule[index][lane] = u64::from_be_bytes(*word);
+            }
+            schedule[4][lane] = 1 << 63;
+            schedule[15][lane] = (SECRET_KEY_LENGTH * 8) as u64;
+        }
+
+        for index in 16..80 {
+            schedule[index] = std::array::from_fn(|lane| {
+                small_sigma1(schedule[index - 2][lane])
+                    .wrapping_add(schedule[index - 7][lane])
+                    .wrapping_add(small_sigma0(schedule[index - 15][lane]))
+                    .wrapping_add(schedule[index - 16][lane])
+            });
+        }
+
+        let mut state: [Word; 8] = INITIAL_STATE.map(|word| [word; LANES]);
+        for (round_constant, scheduled) in ROUND_CONSTANTS.iter().zip(&schedule) {
+            let [a, b, c, d, e, f, g, h] = state;
+
+            let temp1: Word = std::array::from_fn(|lane| {
+                h[lane]
+                    .wrapping_add(big_sigma1(e[lane]))
+                    .wrapping_add((e[lane] & f[lane]) ^ (!e[lane] & g[lane]))
+                    .wrapping_add(*round_constant)
+                    .wrapping_add(scheduled[lane])
+            });
+            let temp2: Word = std::array::from_fn(|lane| {
+                big_sigma0(a[lane])
+                    .wrapping_add((a[lane] & b[lane]) ^ (a[lane] & c[lane]) ^ (b[lane] & c[lane]))
+            });
+
+            state = [
+                std::array::from_fn(|lane| temp1[lane].wrapping_add(temp2[lane])),
+                a,
+                b,
+                c,
+                std::array::from_fn(|lane| d[lane].wrapping_add(temp1[lane])),
+                e,
+                f,
+                g,
+            ];
+        }
+
+        std::array::from_fn(|lane| {
+            let mut half_digest = [0u8; 32];
+            for (index, bytes) in half_digest.as_chunks_mut::<8>().0.iter_mut().enumerate() {
+                *bytes = state[index][lane]
+                    .wrapping_add(INITIAL_STATE[index])
+                    .to_be_bytes();
+            }
+            half_digest
+        })
+    }
+
+    #[inline(always)]
+    const fn big_sigma0(x: u64) -> u64 {
+        x.rotate_right(28) ^ x.rotate_right(34) ^ x.rotate_right(39)
+    }
+
+    #[inline(always)]
+    const fn big_sigma1(x: u64) -> u64 {
+        x.rotate_right(14) ^ x.rotate_right(18) ^ x.rotate_right(41)
+    }
+
+    #[inline(always)]
+    const fn small_sigma0(x: u64) -> u64 {
+        x.rotate_right(1) ^ x.rotate_right(8) ^ (x >> 7)
+    }
+
+    #[inline(always)]
+    const fn small_sigma1(x: u64) -> u64 {
+        x.rotate_right(19) ^ x.rotate_right(61) ^ (x >> 6)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::SigningKey;
+    use sha2::{Digest, Sha512};
+
+    use super::*;
+
+    fn secret_keys(count: usize) -> Vec<[u8; SECRET_KEY_LENGTH]> {
+        (0..count as u64)
+            .map(|index| {
+                Sha512::digest(index.to_le_bytes())[..SECRET_KEY_LENGTH]
+                    .try_into()
+                    .unwrap()
+            })
+            .collect()
+    }
+
+    fn expected(secret_keys: &[[u8; SECRET_KEY_LENGTH]]) -> Vec<[u8; PUBLIC_KEY_LENGTH]> {
+        secret_keys
+            .iter()
+            .map(|secret_key| {
+                SigningKey::from_bytes(secret_key)
+                    .verifying_key()
+                    .to_bytes()
+            })
+            .collect()
+    }
+
+    fn check(derive: impl Fn(&[[u8; SECRET_KEY_LENGTH]], &mut [[u8; PUBLIC_KEY_LENGTH]])) {
+        let secret_keys = secret_keys(100);
+        let mut public_keys = vec![[0; PUBLIC_KEY_LENGTH]; secret_keys.len()];
+        derive(&secret_keys, &mut public_keys);
+
+        assert_eq!(public_keys, expected(&secret_keys));
+    }
+
+    #[test]
+    fn public_keys_matches_dalek() {
+        // Below, at, just past and many times a chunk of LANES keys.
+        for count in [1, 7, 8, 9, 256] {
+            let secret_keys = secret_keys(count);
+            let mut public_keys = vec![[0; PUBLIC_KEY_LENGTH]; count];
+            super::public_keys(&secret_keys, &mut public_keys);
+
+            assert_eq!(public_keys, expected(&secret_keys), "{count} keys");
+        }
+    }
+
+    #[test]
+    fn baseline_matches_dalek() {
+        check(public_keys_baseline);
+    }
+
+    #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+    #[test]
+    fn avx2_matches_dalek() {
+        if is_x86_feature_detected!("avx2") {
+            // SAFETY: the CPU supports AVX2.
+            check(|secret_keys, public_keys| unsafe { public_keys_avx2(secret_keys, public_keys) });
+        }
+    }
+
+    #[cfg(target_arch = "x86_64")]
+    #[test]
+    fn avx512_matches_dalek() {
+        if SimdLevel::detected() == SimdLevel::Avx512 {
+            // SAFETY: the CPU supports AVX-512 with IFMA.
+            check(|secret_keys, public_keys| unsafe {
+                public_keys_avx512(secret_keys, public_keys)
+            });
+        }
+    }
+}
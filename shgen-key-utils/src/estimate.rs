@@ -0,0 +1,163 @@
+use std::ops::Range;
+
+use shgen_config_core::{
+    Config,
+    expression::Expression,
+    keyword::Keyword,
+    search::{SearchFields, matching::KeywordSyntax},
+};
+
+use crate::openssh::layout::{CharacterSet, field_template, variable_range};
+
+/// Probability that a single random key matches `config`.
+///
+/// Every variable character is assumed to be independent and uniformly
+/// distributed over the values its bits allow, so the result is an estimate.
+/// Returns `None` for regular expression keywords, which can't be estimated.
+#[must_use]
+pub fn match_probability(config: &Config) -> Option<f64> {
+    let matching = &config.search.matching;
+    if matching.syntax == KeywordSyntax::Regex {
+        return None;
+    }
+
+    if let Some(expression) = &config.search.expression {
+        return Some(expression_probability(expression, matching.case_sensitive));
+    }
+
+    let field_probabilities = config.field_searches().map(|search| {
+        let template = field_template(search.field);
+        let variable_range = variable_range(&template);
+        let keyword_probabilities = search.keywords.iter().map(|keyword| {
+            let case_sensitive = keyword.is_case_sensitive(matching.case_sensitive);
+            probability_in_template(keyword, case_sensitive, &template, &variable_range)
+        });
+
+        if search.all_keywords {
+            keyword_probabilities.product()
+        } else {
+            any_of(keyword_probabilities)
+        }
+    });
+
+    if matching.all_fields {
+        Some(field_probabilities.product())
+    } else {
+        Some(any_of(field_probabilities))
+    }
+}
+
+/// Probability that the literal `keyword` appears in `field` of a single random key.
+///
+/// `case_sensitive` is the global default, which the keyword may override.
+#[must_use]
+pub fn keyword_probability(keyword: &Keyword, field: &SearchFields, case_sensitive: bool) -> f64 {
+    let template = field_template(field);
+    probability_in_template(
+        keyword,
+        keyword.is_case_sensitive(case_sensitive),
+        &template,
+        &variable_range(&template),
+    )
+}
+
+/// Probability that `expression` holds for a single random key.
+///
+/// Terms are assumed to be independent, even when they search the same field.
+#[must_use]
+pub fn expression_probability(expression: &Expression, case_sensitive: bool) -> f64 {
+    match expression {
+        Expression::Term { keyword, field } => keyword_probability(keyword, field, case_sensitive),
+        Expression::And(operands) => operands
+            .iter()
+            .map(|operand| expression_probability(operand, case_sensitive))
+            .product(),
+        Expression::Or(operands) => any_of(
+            operands
+                .iter()
+                .map(|operand| expression_probability(operand, case_sensitive)),
+        ),
+        Expression::Not(operand) => 1.0 - expression_probability(operand, case_sensitive),
+    }
+}
+
+#[must_use]
+pub fn expected_attempts(probability: f64) -> f64 {
+    1.0 / probability
+}
+
+/// Number of keys to try for a match to have happened with the given `chance`.
+#[must_use]
+pub fn attempts_for_chance(probability: f64, chance: f64) -> f64 {
+    ((-chance).ln_1p() / (-probability).ln_1p()).max(1.0)
+}
+
+fn any_of(probabilities: impl Iterator<Item = f64>) -> f64 {
+    // Computed in log space, as `1.0 - (1.0 - p)` is zero for tiny `p`.
+    -probabilities.map(|p| (-p).ln_1p()).sum::<f64>().exp_m1()
+}
+
+fn probability_in_template(
+    keyword: &Keyword,
+    case_sensitive: bool,
+    template: &[CharacterSet],
+    variable_range: &Range<usize>,
+) -> f64 {
+    let text = keyword.text.as_bytes();
+    if text.is_empty() {
+        return 1.0;
+    }
+
+    let keyword_sets: Vec<CharacterSet> = text
+        .iter()
+        .map(|&character| {
+            if case_sensitive {
+                exact_set(character)
+            } else {
+                case_insensitive_set(character)
+            }
+        })
+        .collect();
+
+    let is_allowed_at = |start: usize| {
+        keyword.anchor.is_none_or(|anchor| {
+            start >= variable_range.start
+                && anchor.allows(
+                    start - variable_range.start,
+                    text.len(),
+                    variable_range.len(),
+                )
+        })
+    };
+
+    let windows = template.windows(text.len()).enumerate();
+    any_of(
+        windows
+            .filter(|&(start, _)| is_allowed_at(start))
+            .map(|(_, window)| {
+                window
+                    .iter()
+                    .zip(&keyword_sets)
+                    .map(|(&allowed, &wanted)| {
+                        f64::from((allowed & wanted).count_ones()) / f64::from(allowed.count_ones())
+                    })
+                    .product()
+            }),
+    )
+}
+
+fn exact_set(character: u8) -> CharacterSet {
+    if character.is_ascii() {
+        1 << character
+    } else {
+        0
+    }
+}
+
+fn case_insensitive_set(character: u8) -> CharacterSet {
+    if character.is_ascii() {
+        (1 << character.to_ascii_lowercase()) | (1 << character.to_ascii_uppercase())
+    } else {
+        0
+    }
+}
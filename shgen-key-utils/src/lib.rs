@@ -1,2 +1,5 @@
+pub mod derive;
+pub mod estimate;
 pub mod matcher;
 pub mod openssh;
+pub mod simd;
@@ -0,0 +1,206 @@
+//! Anchored keywords compiled into masks over the bytes a field encodes.
+//!
+//! Each base64 character of a field stands for six fixed bits of the public
+//! key blob or digest it encodes. A keyword that can only start at a few
+//! positions is then a few mask-and-compare checks on those bytes, and the
+//! text never has to be encoded.
+
+use std::ops::Range;
+
+use shgen_config_core::keyword::{Anchor, Keyword};
+
+use crate::openssh::layout::BASE64_ALPHABET;
+
+use super::pattern_set::PatternSet;
+
+/// Above this many alternatives in a field, the text is searched instead.
+const MAX_ALTERNATIVES: usize = 64;
+
+/// Alternatives of each keyword of a field, indexed by pattern ID.
+pub struct BitPatterns(Vec<Vec<BitPattern>>);
+
+/// Bits that `data[start..]` must have wherever `mask` is set.
+struct BitPattern {
+    start: usize,
+    mask: Box<[u8]>,
+    value: Box<[u8]>,
+}
+
+impl BitPatterns {
+    /// Compiles the keywords of a field, or returns `None` if any of them
+    /// isn't anchored to a few positions, which the text search handles better.
+    ///
+    /// The field's text has the base64 encoding of `data_len` bytes from
+    /// `text_offset` on. Each keyword turns into one alternative per position
+    /// it may start at and per combination of letter cases it may match in.
+    pub fn new(
+        keywords: &[Keyword],
+        case_sensitive: &[bool],
+        variable_range: &Range<usize>,
+        (text_offset, data_len): (usize, usize),
+    ) -> Option<Self> {
+        let mut alternatives = 0;
+        let mut patterns = Vec::with_capacity(keywords.len());
+
+        for (keyword, &case_sensitive) in keywords.iter().zip(case_sensitive) {
+            let anchor = keyword.anchor?;
+            let length = keyword.text.len();
+            let variable_length = variable_range.len();
+
+            let candidates = match anchor {
+                Anchor::Start => 0..=0,
+                Anchor::End => {
+                    let offset = variable_length.saturating_sub(length);
+                    offset..=offset
+                }
+                Anchor::Offset(offset) => offset..=offset,
+                Anchor::Range { from, to } => from..=to.min(variable_length),
+            };
+            let offsets: Vec<usize> = candidates
+                .filter(|&offset| anchor.allows(offset, length, variable_length))
+                .take(MAX_ALTERNATIVES + 1)
+                .collect();
+
+            // A character outside the alphabet can't appear in the encoding,
+            // so the keyword has no alternatives at all.
+            let sextets: Option<Vec<Vec<u8>>> = keyword
+                .text
+                .bytes()
+                .map(|character| sextet_values(character, case_sensitive))
+                .collect();
+            let (sextets, combinations) = match sextets {
+                Some(sextets) => {
+                    let combinations = sextets
+                        .iter()
+                        .try_fold(1usize, |product, values| product.checked_mul(values.len()))?;
+                    (sextets, combinations)
+                }
+                None => (Vec::new(), 0),
+            };
+            alternatives += offsets.len().checked_mul(combinations)?;
+            if alternatives > MAX_ALTERNATIVES {
+                return None;
+            }
+
+            let mut keyword_patterns = Vec::new();
+            for offset in offsets {
+                let first_character = variable_range.start + offset - text_offset;
+                for combination in 0..combinations {
+                    let mut rest = combination;
+                    let characters: Vec<u8> = sextets
+                        .iter()
+                        .map(|values| {
+                            let value = values[rest % values.len()];
+                            rest /= values.len();
+                            value
+                        })
+                        .collect();
+
+                    keyword_patterns.extend(BitPattern::new(
+                        first_character,
+                        &characters,
+                        data_len,
+                    ));
+                }
+            }
+
+            patterns.push(keyword_patterns);
+        }
+
+        Some(Self(patterns))
+    }
+
+    /// Whether any keyword, or every keyword if `all_keywords`, is found in
+    /// the encoded bytes.
+    #[inline]
+    pub fn matches(&self, data: &[u8], all_keywords: bool) -> bool {
+        let found = |alternatives: &Vec<BitPattern>| {
+            alternatives
+                .iter()
+                .any(|alternative| alternative.matches(data))
+        };
+
+        if all_keywords {
+            self.0.iter().all(found)
+        } else {
+            self.0.iter().any(found)
+        }
+    }
+
+    /// Adds the keywords found in the encoded bytes to `found`.
+    pub fn found_patterns(&self, data: &[u8], found: &mut PatternSet) {
+        for (id, alternatives) in self.0.iter().enumerate() {
+            if alternatives
+                .iter()
+                .any(|alternative| alternative.matches(data))
+            {
+                found.insert(id);
+            }
+        }
+    }
+}
+
+impl BitPattern {
+    /// The bits of `characters` placed from the base64 character at
+    /// `first_character` of the encoding of `data_len` bytes, or `None` if
+    /// they don't fit the zero bits that pad the last character.
+    fn new(first_character: usize, characters: &[u8], data_len: usize) -> Option<Self> {
+        let first_bit = first_character * 6;
+        let start = (first_bit / 8).min(data_len);
+        let end = ((first_bit + characters.len() * 6).div_ceil(8)).min(data_len);
+
+        let mut mask = vec![0u8; end - start].into_boxed_slice();
+        let mut value = vec![0u8; end - start].into_boxed_slice();
+
+        for (index, &character) in characters.iter().enumerate() {
+            for bit_index in 0..6 {
+                let bit = (character >> (5 - bit_index)) & 1;
+                let position = first_bit + index * 6 + bit_index;
+
+                if position >= data_len * 8 {
+                    if bit == 1 {
+                        return None;
+                    }
+                    continue;
+                }
+
+                let shift = 7 - position % 8;
+                mask[position / 8 - start] |= 1 << shift;
+                value[position / 8 - start] |= bit << shift;
+            }
+        }
+
+        Some(Self { start, mask, value })
+    }
+
+    #[inline]
+    fn matches(&self, data: &[u8]) -> bool {
+        data[self.start..self.start + self.mask.len()]
+            .iter()
+            .zip(&self.mask)
+            .zip(&self.value)
+            .all(|((byte, mask), value)| byte & mask == *value)
+    }
+}
+
+/// Values of the base64 characters that `character` matches.
+fn sextet_values(character: u8, case_sensitive: bool) -> Option<Vec<u8>> {
+    let value_of = |character: u8| {
+        BASE64_ALPHABET
+            .iter()
+            .position(|&other| other == character)
+            .map(|value| value as u8)
+    };
+
+    let mut values: Vec<u8> = value_of(character).into_iter().collect();
+    if !case_sensitive && character.is_ascii_alphabetic() {
+        let other_case = if character.is_ascii_lowercase() {
+            character.to_ascii_uppercase()
+        } else {
+            character.to_ascii_lowercase()
+        };
+        values.extend(value_of(other_case));
+    }
+
+    (!values.is_empty()).then_some(values)
+}
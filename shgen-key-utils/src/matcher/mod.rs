@@ -0,0 +1,609 @@
+mod bit_pattern;
+mod pattern_set;
+
+use std::{collections::HashMap, ops::Range};
+
+use shgen_config_core::{
+    Config,
+    expression::Expression,
+    keyword::{self, Anchor, Keyword},
+    search::{self, FieldSearch, matching::KeywordSyntax, scoring},
+};
+use shgen_types::{OpenSSHPrivateKey, OpenSSHPublicKey};
+
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, Match};
+use regex::{Regex, RegexSet};
+
+use crate::openssh::{self, Fingerprint, layout};
+
+use bit_pattern::BitPatterns;
+use pattern_set::PatternSet;
+
+/// A keyword found in a field of a key.
+#[derive(Clone, Debug)]
+pub struct KeywordMatch {
+    pub keyword: String,
+    pub field: search::SearchFields,
+    /// Byte offsets of every occurrence in the searched text: the public
+    /// key line, the private key file, or the fingerprint after its
+    /// `SHA256:` style prefix.
+    pub offsets: Vec<usize>,
+}
+
+pub struct Matcher {
+    fields: Vec<FieldMatcher>,
+    plan: Plan,
+}
+
+/// How the results of the field matchers combine into a match.
+enum Plan {
+    /// The field matches its keywords according to its own `all_keywords`.
+    Field(usize),
+    /// The pattern is found in the field, both given by index.
+    Keyword {
+        field: usize,
+        pattern: usize,
+    },
+    All(Vec<Plan>),
+    Any(Vec<Plan>),
+    Not(Box<Plan>),
+}
+
+/// Buffers a [`Matcher`] reuses from one key to the next, so that matching
+/// doesn't allocate. Each worker keeps its own, from [`Matcher::scratch`].
+pub struct Scratch {
+    /// Patterns found in each field, sized for its patterns.
+    found: Vec<PatternSet>,
+    /// Whether `found` holds the patterns of the current key, per field, so
+    /// that every field is formatted and searched at most once.
+    searched: Vec<bool>,
+}
+
+/// The key being evaluated against a [`Plan`].
+struct Evaluation<'a> {
+    formatter: &'a mut openssh::format::Formatter,
+    scratch: &'a mut Scratch,
+}
+
+/// Distinct keywords an expression searches in a field, and the pattern
+/// ID of each.
+struct FieldKeywords<'a> {
+    field: &'a search::SearchFields,
+    keywords: Vec<Keyword>,
+    ids: HashMap<&'a str, usize>,
+}
+
+/// Keywords of a single field, compiled into one automaton.
+struct FieldMatcher {
+    field: search::SearchFields,
+    patterns: Patterns,
+    /// Text of each keyword, indexed by pattern ID.
+    keywords: Vec<String>,
+    all_keywords: bool,
+    /// Anchor of each pattern, indexed by pattern ID.
+    anchors: Vec<Option<Anchor>>,
+    /// Text of each pattern that has to be checked for exact case after the
+    /// case-insensitive automaton found it, indexed by pattern ID.
+    exact_case: Vec<Option<String>>,
+    /// Whether any pattern needs its matches checked against `anchors` or
+    /// `exact_case`, so that the first match found isn't enough.
+    verifies_matches: bool,
+    /// Varying part of the field.
+    variable_range: Range<usize>,
+    /// Part of the field that regex keywords are matched against.
+    base64_range: Range<usize>,
+    /// Global case sensitivity, for comparing repeated characters.
+    case_sensitive: bool,
+    /// The keywords compiled into masks over the encoded bytes, when they
+    /// are all anchored, so that the text doesn't have to be searched.
+    bit_patterns: Option<BitPatterns>,
+}
+
+enum Patterns {
+    AhoCorasick(AhoCorasick),
+    Regex(RegexSet),
+}
+
+impl Matcher {
+    #[must_use]
+    pub fn new(config: &Config) -> Self {
+        if let Some(expression) = &config.search.expression {
+            return Self::from_expression(expression, &config.search.matching);
+        }
+
+        let matching = &config.search.matching;
+        let fields: Vec<FieldMatcher> = config
+            .field_searches()
+            .map(|search| FieldMatcher::new(search, matching.syntax, matching.case_sensitive))
+            .collect();
+
+        let field_plans = (0..fields.len()).map(Plan::Field).collect();
+        let plan = if matching.all_fields {
+            Plan::All(field_plans)
+        } else {
+            Plan::Any(field_plans)
+        };
+
+        Self { fields, plan }
+    }
+
+    /// Builds one automaton per field over the distinct keywords the
+    /// expression searches in it, and turns each term into a lookup.
+    fn from_expression(expression: &Expression, matching: &search::matching::Config) -> Self {
+        let mut field_keywords: Vec<FieldKeywords<'_>> = Vec::new();
+        for (keyword, field) in expression.terms() {
+            let index = match field_keywords.iter().position(|other| other.field == field) {
+                Some(index) => index,
+                None => {
+                    field_keywords.push(FieldKeywords {
+                        field,
+                        keywords: Vec::new(),
+                        ids: HashMap::new(),
+                    });
+                    field_keywords.len() - 1
+                }
+            };
+
+            let FieldKeywords { keywords, ids, .. } = &mut field_keywords[index];
+            ids.entry(keyword.text.as_str()).or_insert_with(|| {
+                keywords.push(keyword.clone());
+                keywords.len() - 1
+            });
+        }
+
+        let plan = Self::compile(expression, &field_keywords);
+        let fields = field_keywords
+            .iter()
+            .map(|field_keywords| {
+                let search = FieldSearch {
+                    field: field_keywords.field,
+                    keywords: &field_keywords.keywords,
+                    all_keywords: false,
+                };
+                FieldMatcher::new(search, matching.syntax, matching.case_sensitive)
+            })
+            .collect();
+
+        Self { fields, plan }
+    }
+
+    fn compile(expression: &Expression, field_keywords: &[FieldKeywords<'_>]) -> Plan {
+        let compile_all = |operands: &[Expression]| {
+            operands
+                .iter()
+                .map(|operand| Self::compile(operand, field_keywords))
+                .collect()
+        };
+
+        match expression {
+            Expression::Term { keyword, field } => {
+                let field_index = field_keywords
+                    .iter()
+                    .position(|other| other.field == field)
+                    .unwrap();
+
+                Plan::Keyword {
+                    field: field_index,
+                    pattern: field_keywords[field_index].ids[keyword.text.as_str()],
+                }
+            }
+            Expression::And(operands) => Plan::All(compile_all(operands)),
+            Expression::Or(operands) => Plan::Any(compile_all(operands)),
+            Expression::Not(operand) => Plan::Not(Box::new(Self::compile(operand, field_keywords))),
+        }
+    }
+
+    /// Empty buffers for one worker to match keys with.
+    #[must_use]
+    pub fn scratch(&self) -> Scratch {
+        Scratch {
+            found: self
+                .fields
+                .iter()
+                .map(|field| PatternSet::new(field.keywords.len()))
+                .collect(),
+            searched: vec![false; self.fields.len()],
+        }
+    }
+
+    pub fn search_matches(
+        &self,
+        formatter: &mut openssh::format::Formatter,
+        scratch: &mut Scratch,
+    ) -> Option<(OpenSSHPublicKey, OpenSSHPrivateKey)> {
+        if self.is_match(formatter, scratch) {
+            // The formatter hands out the same text that was searched.
+            let public_key = formatter.format_public_key();
+            let private_key = formatter.format_private_key();
+
+            Some((public_key, private_key))
+        } else {
+            None
+        }
+    }
+
+    /// Whether the current key matches, without formatting it.
+    pub fn is_match(
+        &self,
+        formatter: &mut openssh::format::Formatter,
+        scratch: &mut Scratch,
+    ) -> bool {
+        scratch.searched.fill(false);
+
+        let mut evaluation = Evaluation { formatter, scratch };
+        self.evaluate(&self.plan, &mut evaluation)
+    }
+
+    /// Where each keyword is found in the current key, by field and in
+    /// the order of the keywords.
+    pub fn keyword_matches(&self, formatter: &mut openssh::format::Formatter) -> Vec<KeywordMatch> {
+        self.fields
+            .iter()
+            .flat_map(|field| field.keyword_matches(formatter))
+            .collect()
+    }
+
+    /// Scores the current key by the weighted criteria of `scoring`,
+    /// summed over every searched field.
+    pub fn score(
+        &self,
+        scoring: &scoring::Config,
+        formatter: &mut openssh::format::Formatter,
+        scratch: &mut Scratch,
+    ) -> f64 {
+        self.fields
+            .iter()
+            .zip(&mut scratch.found)
+            .map(|(field, found)| {
+                field.with_haystack(formatter, |haystack| field.score(scoring, haystack, found))
+            })
+            .sum()
+    }
+
+    fn evaluate(&self, plan: &Plan, evaluation: &mut Evaluation<'_>) -> bool {
+        match plan {
+            Plan::Field(index) => self.fields[*index]
+                .search(evaluation.formatter, &mut evaluation.scratch.found[*index]),
+            Plan::Keyword { field, pattern } => {
+                let Evaluation { formatter, scratch } = evaluation;
+
+                if !scratch.searched[*field] {
+                    self.fields[*field].found_patterns(formatter, &mut scratch.found[*field]);
+                    scratch.searched[*field] = true;
+                }
+
+                scratch.found[*field].contains(*pattern)
+            }
+            Plan::All(plans) => plans.iter().all(|plan| self.evaluate(plan, evaluation)),
+            Plan::Any(plans) => plans.iter().any(|plan| self.evaluate(plan, evaluation)),
+            Plan::Not(plan) => !self.evaluate(plan, evaluation),
+        }
+    }
+}
+
+impl FieldMatcher {
+    fn new(search: FieldSearch<'_>, syntax: KeywordSyntax, case_sensitive: bool) -> Self {
+        let keywords = search.keywords;
+        let case_sensitive_default = case_sensitive;
+
+        let case_sensitive: Vec<bool> = keywords
+            .iter()
+            .map(|keyword| keyword.is_case_sensitive(case_sensitive))
+            .collect();
+        // A single automaton can't mix both, so when only some keywords are
+        // case-sensitive their matches are checked for exact case afterwards.
+        let all_case_sensitive = case_sensitive.iter().all(|&sensitive| sensitive);
+
+        let texts = keywords.iter().map(|keyword| &keyword.text);
+        let patterns = match syntax {
+            KeywordSyntax::Literal => Patterns::AhoCorasick(
+                AhoCorasickBuilder::new()
+                    .ascii_case_insensitive(!all_case_sensitive)
+                    .build(texts)
+                    .unwrap(),
+            ),
+            KeywordSyntax::Regex => Patterns::Regex(
+                keyword::regex_set(keywords, case_sensitive_default)
+                    .expect("regex keywords are validated"),
+            ),
+        };
+
+        let anchors: Vec<Option<Anchor>> = keywords.iter().map(|keyword| keyword.anchor).collect();
+        let exact_case: Vec<Option<String>> = keywords
+            .iter()
+            .zip(&case_sensitive)
+            .map(|(keyword, &sensitive)| {
+                (sensitive && !all_case_sensitive).then(|| keyword.text.clone())
+            })
+            .collect();
+        let verifies_matches =
+            anchors.iter().any(Option::is_some) || exact_case.iter().any(Option::is_some);
+
+        let variable_range = layout::variable_range(&layout::field_template(search.field));
+        let bit_patterns = match syntax {
+            KeywordSyntax::Literal => layout::base64_encoding(search.field).and_then(|encoding| {
+                BitPatterns::new(keywords, &case_sensitive, &variable_range, encoding)
+            }),
+            KeywordSyntax::Regex => None,
+        };
+
+        Self {
+            field: search.field.clone(),
+            patterns,
+            keywords: keywords
+                .iter()
+                .map(|keyword| keyword.text.clone())
+                .collect(),
+            all_keywords: search.all_keywords,
+            anchors,
+            exact_case,
+            verifies_matches,
+            variable_range,
+            base64_range: layout::base64_range(search.field),
+            case_sensitive: case_sensitive_default,
+            bit_patterns,
+        }
+    }
+
+    /// Whether the field matches its keywords, using `seen` to track them.
+    fn search(&self, formatter: &mut openssh::format::Formatter, seen: &mut PatternSet) -> bool {
+        if let Some(bit_patterns) = &self.bit_patterns {
+            return self.with_encoded_bytes(formatter, |data| {
+                bit_patterns.matches(data, self.all_keywords)
+            });
+        }
+
+        self.with_haystack(formatter, |haystack| self.matches(haystack, seen))
+    }
+
+    /// Where each of the keywords is found in the text of the field.
+    fn keyword_matches(&self, formatter: &mut openssh::format::Formatter) -> Vec<KeywordMatch> {
+        let mut offsets = vec![Vec::new(); self.keywords.len()];
+        self.with_haystack(formatter, |haystack| match &self.patterns {
+            Patterns::AhoCorasick(aho_corasick) => {
+                for mat in aho_corasick.find_overlapping_iter(haystack) {
+                    let id = mat.pattern().as_usize();
+                    if self.is_anchored_at(id, &mat) && self.has_exact_case(id, &mat, haystack) {
+                        offsets[id].push(mat.start());
+                    }
+                }
+            }
+            Patterns::Regex(regex_set) => {
+                let base64 = &haystack[self.base64_range.clone()];
+                for id in regex_set.matches(base64).iter() {
+                    let regex = Regex::new(&regex_set.patterns()[id])
+                        .expect("pattern compiled into the set");
+                    offsets[id].extend(
+                        regex
+                            .find_iter(base64)
+                            .map(|mat| self.base64_range.start + mat.start()),
+                    );
+                }
+            }
+        });
+
+        self.keywords
+            .iter()
+            .zip(offsets)
+            .filter(|(_, offsets)| !offsets.is_empty())
+            .map(|(keyword, offsets)| KeywordMatch {
+                keyword: keyword.clone(),
+                field: self.field.clone(),
+                offsets,
+            })
+            .collect()
+    }
+
+    /// Replaces `found` with the patterns found in the field.
+    fn found_patterns(&self, formatter: &mut openssh::format::Formatter, found: &mut PatternSet) {
+        found.clear();
+
+        if let Some(bit_patterns) = &self.bit_patterns {
+            return self.with_encoded_bytes(formatter, |data| {
+                bit_patterns.found_patterns(data, found);
+            });
+        }
+
+        self.with_haystack(formatter, |haystack| match &self.patterns {
+            Patterns::AhoCorasick(aho_corasick) => {
+                let patterns = aho_corasick.patterns_len();
+                let mut found_count = 0;
+
+                for mat in aho_corasick.find_overlapping_iter(haystack) {
+                    let id = mat.pattern().as_usize();
+                    if self.is_anchored_at(id, &mat)
+                        && self.has_exact_case(id, &mat, haystack)
+                        && found.insert(id)
+                    {
+                        found_count += 1;
+                        if found_count == patterns {
+                            break;
+                        }
+                    }
+                }
+            }
+            Patterns::Regex(regex_set) => {
+                let base64 = &haystack[self.base64_range.clone()];
+                for id in regex_set.matches(base64).iter() {
+                    found.insert(id);
+                }
+            }
+        });
+    }
+
+    fn score(&self, scoring: &scoring::Config, haystack: &str, found: &mut PatternSet) -> f64 {
+        let mut distinct_keywords = 0;
+        let mut longest_keyword = 0;
+        let mut earliest_offset = None;
+
+        if let Patterns::AhoCorasick(aho_corasick) = &self.patterns {
+            found.clear();
+
+            for mat in aho_corasick.find_overlapping_iter(haystack) {
+                let id = mat.pattern().as_usize();
+                // Matches in the constant prefix are the same for every key.
+                if mat.start() < self.variable_range.start
+                    || !self.is_anchored_at(id, &mat)
+                    || !self.has_exact_case(id, &mat, haystack)
+                {
+                    continue;
+                }
+
+                if found.insert(id) {
+                    distinct_keywords += 1;
+                }
+                longest_keyword = longest_keyword.max(mat.len());
+
+                let offset = mat.start() - self.variable_range.start;
+                earliest_offset =
+                    Some(earliest_offset.map_or(offset, |earliest: usize| earliest.min(offset)));
+            }
+        }
+
+        let earliest_position = earliest_offset.map_or(0.0, |offset| {
+            1.0 - offset as f64 / self.variable_range.len() as f64
+        });
+
+        let repeated_characters = if scoring.repeated_characters == 0.0 {
+            0
+        } else {
+            self.longest_run(&haystack.as_bytes()[self.variable_range.clone()])
+        };
+
+        scoring.longest_keyword * longest_keyword as f64
+            + scoring.distinct_keywords * f64::from(distinct_keywords)
+            + scoring.earliest_position * earliest_position
+            + scoring.repeated_characters * repeated_characters as f64
+    }
+
+    /// Length of the longest run of a single repeated character.
+    fn longest_run(&self, text: &[u8]) -> usize {
+        let is_same = |a: &u8, b: &u8| {
+            if self.case_sensitive {
+                a == b
+            } else {
+                a.eq_ignore_ascii_case(b)
+            }
+        };
+
+        text.chunk_by(is_same).map(<[u8]>::len).max().unwrap_or(0)
+    }
+
+    fn with_haystack<T>(
+        &self,
+        formatter: &mut openssh::format::Formatter,
+        f: impl FnOnce(&str) -> T,
+    ) -> T {
+        let haystack = match &self.field {
+            search::SearchFields::PublicKey => formatter.public_key_text(),
+            search::SearchFields::PrivateKey => formatter.private_key_text(),
+            search::SearchFields::Sha1Fingerprint => formatter.fingerprint_text(Fingerprint::Sha1),
+            search::SearchFields::Sha256Fingerprint => {
+                formatter.fingerprint_text(Fingerprint::Sha256)
+            }
+            search::SearchFields::Sha384Fingerprint => {
+                formatter.fingerprint_text(Fingerprint::Sha384)
+            }
+            search::SearchFields::Sha512Fingerprint => {
+                formatter.fingerprint_text(Fingerprint::Sha512)
+            }
+        };
+
+        f(haystack)
+    }
+
+    /// Calls `f` with the bytes that the field's text encodes in base64.
+    /// Only fields that have [`BitPatterns`] are passed.
+    fn with_encoded_bytes<T>(
+        &self,
+        formatter: &mut openssh::format::Formatter,
+        f: impl FnOnce(&[u8]) -> T,
+    ) -> T {
+        let fingerprint = match &self.field {
+            search::SearchFields::PublicKey => return f(&formatter.public_key_blob()),
+            search::SearchFields::PrivateKey => {
+                unreachable!("the private key isn't a single base64 encoding")
+            }
+            search::SearchFields::Sha1Fingerprint => Fingerprint::Sha1,
+            search::SearchFields::Sha256Fingerprint => Fingerprint::Sha256,
+            search::SearchFields::Sha384Fingerprint => Fingerprint::Sha384,
+            search::SearchFields::Sha512Fingerprint => Fingerprint::Sha512,
+        };
+
+        f(formatter.fingerprint_digest(fingerprint))
+    }
+
+    #[inline]
+    fn matches(&self, haystack: &str, seen: &mut PatternSet) -> bool {
+        match &self.patterns {
+            Patterns::AhoCorasick(aho_corasick) => {
+                self.matches_aho_corasick(aho_corasick, haystack, seen)
+            }
+            Patterns::Regex(regex_set) => self.matches_regex(regex_set, haystack),
+        }
+    }
+
+    #[inline]
+    fn matches_aho_corasick(
+        &self,
+        aho_corasick: &AhoCorasick,
+        haystack: &str,
+        seen: &mut PatternSet,
+    ) -> bool {
+        if !self.all_keywords && !self.verifies_matches {
+            return aho_corasick.is_match(haystack);
+        }
+
+        let patterns = aho_corasick.patterns_len();
+
+        seen.clear();
+        let mut seen_count = 0;
+
+        // A match that fails verification may overlap one that passes, and
+        // every keyword has to be seen in `all_keywords` mode, so overlapping
+        // matches must not be skipped.
+        aho_corasick.find_overlapping_iter(haystack).any(|mat| {
+            let id = mat.pattern().as_usize();
+            if !self.is_anchored_at(id, &mat) || !self.has_exact_case(id, &mat, haystack) {
+                return false;
+            }
+
+            if seen.insert(id) {
+                seen_count += 1;
+            }
+
+            !self.all_keywords || seen_count == patterns
+        })
+    }
+
+    #[inline]
+    fn is_anchored_at(&self, id: usize, mat: &Match) -> bool {
+        let Some(anchor) = &self.anchors[id] else {
+            return true;
+        };
+
+        mat.start() >= self.variable_range.start
+            && anchor.allows(
+                mat.start() - self.variable_range.start,
+                mat.len(),
+                self.variable_range.len(),
+            )
+    }
+
+    #[inline]
+    fn has_exact_case(&self, id: usize, mat: &Match, haystack: &str) -> bool {
+        self.exact_case[id]
+            .as_ref()
+            .is_none_or(|text| haystack.as_bytes()[mat.range()] == *text.as_bytes())
+    }
+
+    #[inline]
+    fn matches_regex(&self, regex_set: &RegexSet, haystack: &str) -> bool {
+        let base64 = &haystack[self.base64_range.clone()];
+        if self.all_keywords {
+            regex_set.matches(base64).matched_all()
+        } else {
+            regex_set.is_match(base64)
+        }
+    }
+}
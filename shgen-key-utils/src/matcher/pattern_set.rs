@@ -0,0 +1,49 @@
+/// Set of pattern IDs, for any number of patterns.
+///
+/// Up to 64 patterns fit in a single word without allocating,
+/// which keeps the common case fast in the hot loop.
+#[derive(Clone, Debug)]
+pub enum PatternSet {
+    Small(u64),
+    Large(Box<[u64]>),
+}
+
+impl PatternSet {
+    pub fn new(patterns: usize) -> Self {
+        if patterns <= 64 {
+            Self::Small(0)
+        } else {
+            Self::Large(vec![0; patterns.div_ceil(64)].into_boxed_slice())
+        }
+    }
+
+    /// Removes every ID, keeping the allocation.
+    #[inline]
+    pub fn clear(&mut self) {
+        match self {
+            Self::Small(word) => *word = 0,
+            Self::Large(words) => words.fill(0),
+        }
+    }
+
+    /// Adds `id`, returning whether it wasn't in the set yet.
+    #[inline]
+    pub fn insert(&mut self, id: usize) -> bool {
+        let (word, bit) = match self {
+            Self::Small(word) => (word, 1 << id),
+            Self::Large(words) => (&mut words[id / 64], 1 << (id % 64)),
+        };
+
+        let is_new = *word & bit == 0;
+        *word |= bit;
+        is_new
+    }
+
+    #[inline]
+    pub fn contains(&self, id: usize) -> bool {
+        match self {
+            Self::Small(word) => word & (1 << id) != 0,
+            Self::Large(words) => words[id / 64] & (1 << (id % 64)) != 0,
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Passphrase encryption of generated private keys, in the format that
+//! `ssh-keygen -p` writes: the private section is encrypted with a key and
+//! IV derived from the passphrase by `bcrypt_pbkdf`, and everything else,
+//! including the public key, stays readable.
+
+use aes::Aes256;
+use aes_gcm::{Aes256Gcm, KeyInit, Nonce, aead::AeadInPlace};
+use base64::{
+    Engine,
+    engine::general_purpose::{STANDARD, STANDARD_NO_PAD},
+};
+use ctr::cipher::{KeyIvInit, StreamCipher};
+use shgen_types::OpenSSHPrivateKey;
+use zeroize::Zeroizing;
+
+use crate::openssh::format::constants;
+
+/// Length of the random salt, the same as `ssh-keygen` uses.
+pub const SALT_LENGTH: usize = 16;
+
+const KDF: &[u8] = b"bcrypt";
+const KEY_LENGTH: usize = 32;
+/// Block size of both ciphers, which the private section is padded to.
+const BLOCK_SIZE: usize = 16;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Cipher {
+    Aes256Ctr,
+    /// AES-256 in GCM mode, which also authenticates the private section.
+    Aes256Gcm,
+}
+
+impl Cipher {
+    /// Name of the cipher in the key file.
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Aes256Ctr => "aes256-ctr",
+            Self::Aes256Gcm => "aes256-gcm@openssh.com",
+        }
+    }
+
+    const fn iv_len(self) -> usize {
+        match self {
+            Self::Aes256Ctr => 16,
+            Self::Aes256Gcm => 12,
+        }
+    }
+}
+
+/// Encrypts an unencrypted private key made by
+/// [`super::format::Formatter`] with `passphrase`.
+///
+/// # Panics
+///
+/// If `private_key` isn't an unencrypted OpenSSH private key, if
+/// `passphrase` is empty, or if `rounds` is zero.
+#[must_use]
+pub fn encrypt_private_key(
+    private_key: &OpenSSHPrivateKey,
+    cipher: Cipher,
+    rounds: u32,
+    passphrase: &str,
+    salt: [u8; SALT_LENGTH],
+) -> OpenSSHPrivateKey {
+    let blob = decode(private_key);
+    let mut reader = SshReader::new(&blob);
+
+    assert_eq!(reader.bytes(constants::MAGIC.len()), constants::MAGIC);
+    assert_eq!(
+        reader.string(),
+        constants::CIPHER,
+        "key is already encrypted"
+    );
+    reader.string(); // kdf
+    reader.string(); // kdf options
+    let keys = reader.u32();
+    let public_key_blob = reader.string();
+    let section = reader.string();
+
+    // Checkints, then the key type, public key, private key and comment,
+    // followed by padding to the block size of the old cipher.
+    let mut section_reader = SshReader::new(section);
+    section_reader.bytes(8);
+    for _ in 0..4 {
+        section_reader.string();
+    }
+
+    let mut encrypted = Zeroizing::new(section[..section_reader.cursor].to_vec());
+    for padding in 1..=(BLOCK_SIZE - encrypted.len() % BLOCK_SIZE) % BLOCK_SIZE {
+        encrypted.push(padding as u8);
+    }
+
+    let mut key_iv = Zeroizing::new([0u8; KEY_LENGTH + 16]);
+    let key_iv = &mut key_iv[..KEY_LENGTH + cipher.iv_len()];
+    bcrypt_pbkdf::bcrypt_pbkdf(passphrase.as_bytes(), &salt, rounds, key_iv)
+        .expect("passphrase is not empty and rounds are nonzero");
+    let (key, iv) = key_iv.split_at(KEY_LENGTH);
+
+    let tag = match cipher {
+        Cipher::Aes256Ctr => {
+            ctr::Ctr128BE::<Aes256>::new(key.into(), iv.into()).apply_keystream(&mut encrypted);
+            None
+        }
+        Cipher::Aes256Gcm => Some(
+            Aes256Gcm::new(key.into())
+                .encrypt_in_place_detached(Nonce::from_slice(iv), b"", &mut encrypted)
+                .expect("private section is short enough"),
+        ),
+    };
+
+    let mut kdf_options = Vec::new();
+    put_string(&mut kdf_options, &salt);
+    kdf_options.extend_from_slice(&rounds.to_be_bytes());
+
+    let mut encrypted_blob = Vec::new();
+    encrypted_blob.extend_from_slice(constants::MAGIC);
+    put_string(&mut encrypted_blob, cipher.name().as_bytes());
+    put_string(&mut encrypted_blob, KDF);
+    put_string(&mut encrypted_blob, &kdf_options);
+    encrypted_blob.extend_from_slice(&keys.to_be_bytes());
+    put_string(&mut encrypted_blob, public_key_blob);
+    // The tag follows the private section, outside of its length.
+    put_string(&mut encrypted_blob, &encrypted);
+    if let Some(tag) = tag {
+        encrypted_blob.extend_from_slice(&tag);
+    }
+
+    encode(&encrypted_blob)
+}
+
+/// The binary blob inside the armor of a private key file.
+fn decode(private_key: &OpenSSHPrivateKey) -> Zeroizing<Vec<u8>> {
+    let base64: Zeroizing<String> = Zeroizing::new(
+        private_key
+            .strip_prefix(constants::PRIVATE_KEY_HEADER)
+            .and_then(|rest| rest.strip_suffix(constants::PRIVATE_KEY_FOOTER))
+            .expect("private key has the OpenSSH armor")
+            .lines()
+            .collect(),
+    );
+
+    Zeroizing::new(
+        STANDARD_NO_PAD
+            .decode(base64.trim_end_matches('='))
+            .expect("private key is valid base64"),
+    )
+}
+
+/// Armors `blob` as a private key file, padding the base64 like OpenSSH.
+fn encode(blob: &[u8]) -> OpenSSHPrivateKey {
+    let base64 = STANDARD.encode(blob);
+
+    let mut text = String::from(constants::PRIVATE_KEY_HEADER);
+    for line in base64.as_bytes().chunks(constants::PRIVATE_KEY_LINE_LENGTH) {
+        text.push_str(str::from_utf8(line).expect("base64 is ASCII"));
+        text.push('\n');
+    }
+    text.push_str(constants::PRIVATE_KEY_FOOTER);
+
+    OpenSSHPrivateKey::new(text)
+}
+
+fn put_string(buffer: &mut Vec<u8>, string: &[u8]) {
+    buffer.extend_from_slice(&(string.len() as u32).to_be_bytes());
+    buffer.extend_from_slice(string);
+}
+
+struct SshReader<'a> {
+    buffer: &'a [u8],
+    cursor: usize,
+}
+
+impl<'a> SshReader<'a> {
+    const fn new(buffer: &'a [u8]) -> Self {
+        Self { buffer, cursor: 0 }
+    }
+
+    fn bytes(&mut self, len: usize) -> &'a [u8] {
+        let bytes = &self.buffer[self.cursor..self.cursor + len];
+        self.cursor += len;
+        bytes
+    }
+
+    fn u32(&mut self) -> u32 {
+        u32::from_be_bytes(self.bytes(4).try_into().unwrap())
+    }
+
+    fn string(&mut self) -> &'a [u8] {
+        let len = self.u32() as usize;
+        self.bytes(len)
+    }
+}
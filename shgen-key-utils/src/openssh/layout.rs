@@ -0,0 +1,179 @@
+use std::ops::Range;
+
+use ed25519_dalek::{PUBLIC_KEY_LENGTH, SECRET_KEY_LENGTH};
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha384, Sha512};
+use shgen_config_core::search::SearchFields;
+
+use crate::openssh::format::{constants, sizes};
+
+pub const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Set of ASCII characters that can appear at a position of a field.
+pub type CharacterSet = u128;
+
+/// Range of the field's text that differs between keys, from the first to
+/// the last character that isn't the same for every key.
+pub fn variable_range(template: &[CharacterSet]) -> Range<usize> {
+    let is_variable = |set: &CharacterSet| set.count_ones() > 1;
+
+    let start = template.iter().position(is_variable).unwrap_or(0);
+    let end = template
+        .iter()
+        .rposition(is_variable)
+        .map_or(0, |end| end + 1);
+
+    start..end
+}
+
+/// For a field whose text is the unwrapped base64 encoding of the public key
+/// blob or a digest, the offset in the text where the encoding starts and
+/// the number of bytes it encodes.
+pub fn base64_encoding(field: &SearchFields) -> Option<(usize, usize)> {
+    match field {
+        SearchFields::PublicKey => Some((constants::ALGORITHM.len() + 1, sizes::PUBLIC_KEY_BLOB)),
+        SearchFields::PrivateKey => None,
+        SearchFields::Sha1Fingerprint => Some((0, <Sha1 as Digest>::output_size())),
+        SearchFields::Sha256Fingerprint => Some((0, <Sha256 as Digest>::output_size())),
+        SearchFields::Sha384Fingerprint => Some((0, <Sha384 as Digest>::output_size())),
+        SearchFields::Sha512Fingerprint => Some((0, <Sha512 as Digest>::output_size())),
+    }
+}
+
+/// Range of the field's text that holds its base64, without the algorithm
+/// name of a public key, or the header, footer and last line break of a
+/// private key.
+pub fn base64_range(field: &SearchFields) -> Range<usize> {
+    let len = field_template(field).len();
+    match field {
+        SearchFields::PublicKey => constants::ALGORITHM.len() + 1..len,
+        SearchFields::PrivateKey => {
+            constants::PRIVATE_KEY_HEADER.len()..len - constants::PRIVATE_KEY_FOOTER.len() - 1
+        }
+        SearchFields::Sha1Fingerprint
+        | SearchFields::Sha256Fingerprint
+        | SearchFields::Sha384Fingerprint
+        | SearchFields::Sha512Fingerprint => 0..len,
+    }
+}
+
+pub fn field_template(field: &SearchFields) -> Vec<CharacterSet> {
+    match field {
+        SearchFields::PublicKey => {
+            let mut template = text_template(constants::ALGORITHM.as_bytes());
+            template.extend(text_template(b" "));
+            template.extend(base64_template(&public_key_blob_layout().0));
+
+            template
+        }
+        SearchFields::PrivateKey => {
+            let mut template = text_template(constants::PRIVATE_KEY_HEADER.as_bytes());
+            for line in base64_template(&private_key_blob_layout().0)
+                .chunks(constants::PRIVATE_KEY_LINE_LENGTH)
+            {
+                template.extend(line);
+                template.extend(text_template(b"\n"));
+            }
+            template.extend(text_template(constants::PRIVATE_KEY_FOOTER.as_bytes()));
+
+            template
+        }
+        SearchFields::Sha1Fingerprint => digest_template(<Sha1 as Digest>::output_size()),
+        SearchFields::Sha256Fingerprint => digest_template(<Sha256 as Digest>::output_size()),
+        SearchFields::Sha384Fingerprint => digest_template(<Sha384 as Digest>::output_size()),
+        SearchFields::Sha512Fingerprint => digest_template(<Sha512 as Digest>::output_size()),
+    }
+}
+
+fn text_template(text: &[u8]) -> Vec<CharacterSet> {
+    text.iter().map(|&character| 1 << character).collect()
+}
+
+fn digest_template(digest_size: usize) -> Vec<CharacterSet> {
+    let mut layout = BitLayout::default();
+    layout.random(digest_size);
+
+    base64_template(&layout.0)
+}
+
+fn base64_template(bits: &[Option<bool>]) -> Vec<CharacterSet> {
+    bits.chunks(6)
+        .map(|sextet| {
+            BASE64_ALPHABET
+                .iter()
+                .enumerate()
+                .filter(|(value, _)| {
+                    sextet.iter().enumerate().all(|(index, bit)| {
+                        bit.is_none_or(|bit| bit == ((value >> (5 - index)) & 1 == 1))
+                    })
+                })
+                .fold(0, |set, (_, &character)| set | (1 << character))
+        })
+        .collect()
+}
+
+fn public_key_blob_layout() -> BitLayout {
+    let mut layout = BitLayout::default();
+
+    layout.string(constants::ALGORITHM.as_bytes());
+    layout.u32(PUBLIC_KEY_LENGTH);
+    layout.random(PUBLIC_KEY_LENGTH);
+
+    layout
+}
+
+fn private_key_blob_layout() -> BitLayout {
+    let mut layout = BitLayout::default();
+
+    layout.bytes(constants::MAGIC);
+    layout.string(constants::CIPHER);
+    layout.string(constants::KDF);
+    layout.u32(constants::KDF_OPTIONS.len());
+    layout.u32(1);
+
+    layout.u32(sizes::PUBLIC_KEY_BLOB);
+    layout.0.extend(public_key_blob_layout().0);
+
+    layout.u32(sizes::private_key_section(0) + sizes::private_key_padding(0));
+    layout.random(8); // check-ints
+    layout.string(constants::ALGORITHM.as_bytes());
+    layout.u32(PUBLIC_KEY_LENGTH);
+    layout.random(PUBLIC_KEY_LENGTH);
+    layout.u32(PUBLIC_KEY_LENGTH + SECRET_KEY_LENGTH);
+    layout.random(PUBLIC_KEY_LENGTH + SECRET_KEY_LENGTH);
+    layout.u32(0); // empty comment
+
+    for i in 1..=sizes::private_key_padding(0) {
+        layout.bytes(&[i as u8]);
+    }
+
+    layout
+}
+
+/// Bits of a binary blob, with `None` for bits that differ between keys.
+#[derive(Default)]
+struct BitLayout(Vec<Option<bool>>);
+
+impl BitLayout {
+    fn bytes(&mut self, bytes: &[u8]) {
+        self.0.extend(
+            bytes
+                .iter()
+                .flat_map(|byte| (0..8).rev().map(move |bit| Some((byte >> bit) & 1 == 1))),
+        );
+    }
+
+    fn u32(&mut self, value: usize) {
+        self.bytes(&(value as u32).to_be_bytes());
+    }
+
+    fn string(&mut self, string: &[u8]) {
+        self.u32(string.len());
+        self.bytes(string);
+    }
+
+    fn random(&mut self, bytes: usize) {
+        self.0.extend(std::iter::repeat_n(None, bytes * 8));
+    }
+}
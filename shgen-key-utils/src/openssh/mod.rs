@@ -1,8 +1,24 @@
+pub mod encrypt;
 pub mod format;
+pub(crate) mod layout;
 
+#[derive(Clone, Copy, Debug)]
 pub enum Fingerprint {
     Sha1,
     Sha256,
     Sha384,
     Sha512,
 }
+
+impl Fingerprint {
+    /// Length of the raw digest in bytes.
+    #[must_use]
+    pub const fn digest_len(self) -> usize {
+        match self {
+            Self::Sha1 => 20,
+            Self::Sha256 => 32,
+            Self::Sha384 => 48,
+            Self::Sha512 => 64,
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Picks the widest vector instructions the CPU supports, once per process.
+//!
+//! The key derivation in [`crate::derive`] and the fingerprint digests in
+//! [`crate::openssh`] are compiled once per [`SimdLevel`], and the variant
+//! for the detected level is picked at runtime.
+
+use std::{fmt, sync::OnceLock};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimdLevel {
+    /// No extensions beyond the target's defaults.
+    Baseline,
+    Avx2,
+    /// AVX-512 with the VL extension, for its 64-bit vector rotates, and
+    /// IFMA, for its 52-bit multiply-accumulates.
+    Avx512,
+}
+
+impl SimdLevel {
+    /// The best level for this CPU, detected on first use.
+    #[must_use]
+    pub fn detected() -> Self {
+        static DETECTED: OnceLock<SimdLevel> = OnceLock::new();
+        *DETECTED.get_or_init(Self::detect)
+    }
+
+    fn detect() -> Self {
+        #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+        {
+            if is_x86_feature_detected!("avx512f")
+                && is_x86_feature_detected!("avx512vl")
+                && is_x86_feature_detected!("avx512ifma")
+            {
+                return Self::Avx512;
+            }
+
+            if is_x86_feature_detected!("avx2") {
+                return Self::Avx2;
+            }
+        }
+
+        Self::Baseline
+    }
+}
+
+impl fmt::Display for SimdLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Baseline => "baseline",
+            Self::Avx2 => "avx2",
+            Self::Avx512 => "avx-512 ifma",
+        };
+
+        f.pad(name)
+    }
+}
+
+/// Defines `$name` to call `$body` compiled for the detected [`SimdLevel`].
+///
+/// `$body` must be `#[inline(always)]`, so that it and everything it
+/// inlines is compiled with each backend's target features.
+macro_rules! dispatch {
+    ($(#[$attr:meta])* $vis:vis fn $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)? => $body:path) => {
+        $(#[$attr])*
+        $vis fn $name($($arg: $ty),*) $(-> $ret)? {
+            #[cfg(any(target_arch = "x86_64", target_arch = "x86"))]
+            {
+                #[target_feature(enable = "avx2")]
+                fn avx2($($arg: $ty),*) $(-> $ret)? {
+                    $body($($arg),*)
+                }
+
+                #[target_feature(enable = "avx512f,avx512vl,avx512ifma")]
+                fn avx512($($arg: $ty),*) $(-> $ret)? {
+                    $body($($arg),*)
+                }
+
+                match $crate::simd::SimdLevel::detected() {
+                    // SAFETY: the CPU supports the enabled target features.
+                    $crate::simd::SimdLevel::Avx512 => return unsafe { avx512($($arg),*) },
+                    // SAFETY: the CPU supports the enabled target features.
+                    $crate::simd::SimdLevel::Avx2 => return unsafe { avx2($($arg),*) },
+                    $crate::simd::SimdLevel::Baseline => {}
+                }
+            }
+
+            $body($($arg),*)
+        }
+    };
+}
+
+pub(crate) use dispatch;
@@ -19,6 +19,20 @@ impl Rng {
         }
     }
 
+    /// Stream `stream` of a master `seed`, starting `offset` bytes in.
+    ///
+    /// Always ChaCha8, so that a seed gives the same keys on every machine,
+    /// and independent streams can be handed out to every thread.
+    #[must_use]
+    pub fn from_seed_stream(seed: [u8; 32], stream: u64, offset: u64) -> Self {
+        assert!(offset.is_multiple_of(4), "offset must be a whole word");
+
+        let mut rng = ChaCha8Rng::from_seed(seed);
+        rng.set_stream(stream);
+        rng.set_word_pos(u128::from(offset / 4));
+        Self::ChaCha8(rng)
+    }
+
     fn is_aes_available() -> bool {
         #[cfg(target_arch = "x86_64")]
         {
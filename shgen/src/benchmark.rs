@@ -0,0 +1,223 @@
+use std::{
+    fmt,
+    hint::black_box,
+    sync::Barrier,
+    thread,
+    time::{Duration, Instant},
+};
+
+use ed25519_dalek::{PUBLIC_KEY_LENGTH, SECRET_KEY_LENGTH};
+use rand::RngCore as _;
+use shgen_config_native::Config;
+use shgen_key_utils::{
+    derive,
+    matcher::Matcher,
+    openssh::{Fingerprint, format::Formatter},
+    simd::SimdLevel,
+};
+use shgen_rand::Rng;
+
+use crate::{
+    display,
+    generate_keys::{KEYS_PER_BATCH, Worker},
+};
+
+#[derive(Clone, Copy)]
+enum Stage {
+    Worker,
+    KeyDerivation,
+    PublicKey,
+    PrivateKey,
+    Fingerprint(Fingerprint),
+    Matcher,
+}
+
+impl Stage {
+    const ALL: [Self; 9] = [
+        Self::Worker,
+        Self::KeyDerivation,
+        Self::PublicKey,
+        Self::PrivateKey,
+        Self::Fingerprint(Fingerprint::Sha1),
+        Self::Fingerprint(Fingerprint::Sha256),
+        Self::Fingerprint(Fingerprint::Sha384),
+        Self::Fingerprint(Fingerprint::Sha512),
+        Self::Matcher,
+    ];
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Worker => "worker",
+            Self::KeyDerivation => "key derivation",
+            Self::PublicKey => "public key",
+            Self::PrivateKey => "private key",
+            Self::Fingerprint(Fingerprint::Sha1) => "sha1 fingerprint",
+            Self::Fingerprint(Fingerprint::Sha256) => "sha256 fingerprint",
+            Self::Fingerprint(Fingerprint::Sha384) => "sha384 fingerprint",
+            Self::Fingerprint(Fingerprint::Sha512) => "sha512 fingerprint",
+            Self::Matcher => "matcher",
+        };
+
+        f.pad(name)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Limit {
+    Duration(Duration),
+    Keys(u64),
+}
+
+impl Limit {
+    fn is_reached(self, started: Instant, keys: u64) -> bool {
+        match self {
+            Self::Duration(duration) => started.elapsed() >= duration,
+            Self::Keys(limit) => keys >= limit,
+        }
+    }
+}
+
+pub fn benchmark(config: Config) {
+    let thread_counts = if config.benchmark.threads.is_empty() {
+        default_thread_counts(config.runtime.threads)
+    } else {
+        config.benchmark.threads
+    };
+
+    let matcher = Matcher::new(&config.shared);
+
+    println!("Key derivation: {}", SimdLevel::detected());
+    println!();
+    println!("{:>7}  {:<18}  {:>14}", "threads", "stage", "keys/sec");
+    for threads in thread_counts {
+        let limit = match config.benchmark.keys {
+            Some(keys) => Limit::Keys(keys.div_ceil(threads as u64)),
+            None => Limit::Duration(config.benchmark.duration),
+        };
+
+        for stage in Stage::ALL {
+            let keys_per_second = run_stage(stage, threads, limit, &matcher);
+
+            println!(
+                "{threads:>7}  {stage:<18}  {:>14}",
+                display::thousands(keys_per_second as u64)
+            );
+        }
+    }
+}
+
+/// Measures how many keys per second the generation workers go through.
+pub fn measure_worker_rate(threads: usize, duration: Duration, matcher: &Matcher) -> f64 {
+    run_stage(Stage::Worker, threads, Limit::Duration(duration), matcher)
+}
+
+fn default_thread_counts(max_threads: usize) -> Vec<usize> {
+    let mut thread_counts: Vec<usize> = (0..)
+        .map(|exponent| 1 << exponent)
+        .take_while(|&threads| threads < max_threads)
+        .collect();
+    thread_counts.push(max_threads);
+
+    thread_counts
+}
+
+fn run_stage(stage: Stage, threads: usize, limit: Limit, matcher: &Matcher) -> f64 {
+    let barrier = Barrier::new(threads + 1);
+
+    let (keys, elapsed) = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|thread_id| {
+                let barrier = &barrier;
+
+                thread::Builder::new()
+                    .name(format!("shgen-benchmark-{thread_id}"))
+                    .spawn_scoped(scope, move || stage_worker(stage, limit, matcher, barrier))
+                    .expect("failed to spawn benchmark thread")
+            })
+            .collect();
+
+        barrier.wait();
+        let started = Instant::now();
+
+        let keys: u64 = workers
+            .into_iter()
+            .map(|worker| worker.join().expect("benchmark thread panicked"))
+            .sum();
+
+        (keys, started.elapsed())
+    });
+
+    keys as f64 / elapsed.as_secs_f64()
+}
+
+fn stage_worker(stage: Stage, limit: Limit, matcher: &Matcher, barrier: &Barrier) -> u64 {
+    let mut rng = Rng::from_best_available();
+    let mut formatter = Formatter::empty();
+    let mut scratch = matcher.scratch();
+
+    // Stages that don't derive keys themselves cycle through a pool of
+    // already derived ones, so that only the measured stage is timed.
+    let mut key_pool_seeds = [0u8; KEYS_PER_BATCH * SECRET_KEY_LENGTH];
+    rng.fill_bytes(&mut key_pool_seeds);
+
+    let (key_pool, _) = key_pool_seeds.as_chunks::<SECRET_KEY_LENGTH>();
+    let mut key_pool_public = [[0u8; PUBLIC_KEY_LENGTH]; KEYS_PER_BATCH];
+    derive::public_keys(key_pool, &mut key_pool_public);
+    let key_pool: Vec<_> = key_pool.iter().zip(&key_pool_public).collect();
+
+    let mut public_keys_batch = [[0u8; PUBLIC_KEY_LENGTH]; KEYS_PER_BATCH];
+    let mut worker = Worker::new(matcher);
+
+    barrier.wait();
+    let started = Instant::now();
+
+    let mut keys = 0;
+    while !limit.is_reached(started, keys) {
+        match stage {
+            Stage::Worker => {
+                worker.search_batch(|_, key| {
+                    black_box(key);
+                });
+            }
+            Stage::KeyDerivation => {
+                let mut secret_keys_batch = [0u8; KEYS_PER_BATCH * SECRET_KEY_LENGTH];
+                rng.fill_bytes(&mut secret_keys_batch);
+
+                let (secret_keys_chunks, _) = secret_keys_batch.as_chunks::<SECRET_KEY_LENGTH>();
+                derive::public_keys(secret_keys_chunks, &mut public_keys_batch);
+                black_box(&public_keys_batch);
+            }
+            Stage::PublicKey => {
+                for &(secret_key, public_key) in &key_pool {
+                    formatter.update_key_pair(secret_key, public_key);
+                    black_box(formatter.public_key_text());
+                }
+            }
+            Stage::PrivateKey => {
+                for &(secret_key, public_key) in &key_pool {
+                    formatter.update_key_pair(secret_key, public_key);
+                    black_box(formatter.private_key_text());
+                }
+            }
+            Stage::Fingerprint(fingerprint) => {
+                for &(secret_key, public_key) in &key_pool {
+                    formatter.update_key_pair(secret_key, public_key);
+                    black_box(formatter.fingerprint_text(fingerprint));
+                }
+            }
+            Stage::Matcher => {
+                for &(secret_key, public_key) in &key_pool {
+                    formatter.update_key_pair(secret_key, public_key);
+                    black_box(matcher.search_matches(&mut formatter, &mut scratch));
+                }
+            }
+        }
+
+        keys += KEYS_PER_BATCH as u64;
+    }
+
+    black_box(&formatter);
+    keys
+}
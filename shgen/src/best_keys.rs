@@ -0,0 +1,186 @@
+use std::{
+    process::ExitCode,
+    thread,
+    time::{Duration, Instant},
+};
+
+use shgen_config_core::search::scoring;
+use shgen_config_native::Config;
+use shgen_key_utils::{matcher::Matcher, simd::SimdLevel};
+
+use crate::{
+    cancel::{CANCELLED_EXIT_CODE, CancellationToken},
+    display,
+    generate_keys::{self, FoundKey, KEYS_PER_BATCH, Worker},
+    output::{Comment, Discovery, Output},
+    progress::{self, Progress},
+};
+
+/// How often to check for the deadline while workers are scoring keys.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+struct ScoredKey {
+    score: f64,
+    thread_id: usize,
+    key: FoundKey,
+}
+
+/// Scores keys until the timeout, the attempt budget or the score threshold
+/// is reached, then saves the best ones. Cancelling also saves the best keys
+/// found so far.
+pub fn generate_best(config: &Config, output: &Output<'_>, scoring: &scoring::Config) -> ExitCode {
+    if let Err(error) = output.check_available(1) {
+        eprintln!("{error}");
+        return ExitCode::FAILURE;
+    }
+
+    let _keep_awake = generate_keys::keep_awake(config);
+    eprintln!("Deriving keys with {} instructions", SimdLevel::detected());
+
+    let matcher = Matcher::new(&config.shared);
+    let progress = Progress::new(
+        config.runtime.threads,
+        config.runtime.max_attempts,
+        CancellationToken::from_signals(),
+    );
+    let deadline = config
+        .runtime
+        .timeout
+        .map(|timeout| Instant::now() + timeout);
+
+    let mut best_keys: Vec<ScoredKey> = thread::scope(|scope| {
+        let worker_threads: Vec<_> = (0..config.runtime.threads)
+            .map(|thread_id| {
+                let matcher = &matcher;
+                let comment = output.comment();
+                let progress = &progress;
+
+                thread::Builder::new()
+                    .name(format!("shgen-worker-{thread_id}"))
+                    .spawn_scoped(scope, move || {
+                        worker(thread_id, matcher, comment, progress, scoring)
+                    })
+                    .expect("failed to spawn worker thread")
+            })
+            .collect();
+
+        let reporter_thread = thread::Builder::new()
+            .name(String::from("shgen-progress"))
+            .spawn_scoped(scope, || {
+                progress::report(&progress, config.runtime.progress_interval, None);
+            })
+            .expect("failed to spawn progress thread");
+
+        while !progress.is_finished() {
+            match deadline {
+                Some(deadline) if Instant::now() >= deadline => break,
+                Some(deadline) => thread::park_timeout(
+                    deadline
+                        .saturating_duration_since(Instant::now())
+                        .min(POLL_INTERVAL),
+                ),
+                None => thread::park_timeout(POLL_INTERVAL),
+            }
+        }
+
+        progress.finish();
+        reporter_thread.thread().unpark();
+
+        worker_threads
+            .into_iter()
+            .flat_map(|thread| thread.join().expect("worker thread panicked"))
+            .collect()
+    });
+
+    best_keys.sort_by(|a, b| b.score.total_cmp(&a.score));
+    best_keys.truncate(scoring.keep);
+
+    let verb = if progress.is_cancelled() {
+        "Cancelled after scoring"
+    } else {
+        "Scored"
+    };
+    println!(
+        "{verb} {} keys in {}",
+        display::thousands(progress.attempts()),
+        display::duration(progress.elapsed())
+    );
+
+    let mut saved_all = true;
+    for (rank, key) in best_keys.iter().enumerate() {
+        println!(
+            "#{} scored {:.2}: {}",
+            rank + 1,
+            key.score,
+            &*key.key.public_key
+        );
+
+        let discovery = Discovery {
+            thread_id: key.thread_id,
+            attempts: progress.attempts(),
+            elapsed: progress.elapsed(),
+            score: Some(key.score),
+        };
+        if let Err(error) = output.save_keys(rank as u64 + 1, &key.key, &discovery) {
+            eprintln!("{error}");
+            saved_all = false;
+        }
+    }
+
+    if !saved_all {
+        ExitCode::FAILURE
+    } else if progress.is_cancelled() {
+        ExitCode::from(CANCELLED_EXIT_CODE)
+    } else {
+        ExitCode::SUCCESS
+    }
+}
+
+/// Scores keys until the search finishes, returning the best
+/// `scoring.keep` keys this worker found, best first.
+fn worker(
+    thread_id: usize,
+    matcher: &Matcher,
+    comment: &Comment,
+    progress: &Progress,
+    scoring: &scoring::Config,
+) -> Vec<ScoredKey> {
+    let mut worker = Worker::new(matcher).with_comment(comment);
+    let mut best_keys: Vec<ScoredKey> = Vec::with_capacity(scoring.keep + 1);
+
+    let min_score = |best_keys: &[ScoredKey]| {
+        if best_keys.len() < scoring.keep {
+            f64::NEG_INFINITY
+        } else {
+            best_keys[scoring.keep - 1].score
+        }
+    };
+
+    while !progress.is_finished() {
+        worker.score_batch(scoring, min_score(&best_keys), |score, key| {
+            progress.record_score(score);
+            if scoring
+                .threshold
+                .is_some_and(|threshold| score >= threshold)
+            {
+                progress.finish();
+            }
+
+            let rank = best_keys.partition_point(|key| key.score >= score);
+            best_keys.insert(
+                rank,
+                ScoredKey {
+                    score,
+                    thread_id,
+                    key,
+                },
+            );
+            best_keys.truncate(scoring.keep);
+
+            min_score(&best_keys)
+        });
+        progress.add_attempts(thread_id, KEYS_PER_BATCH as u64);
+    }
+
+    best_keys
+}
@@ -0,0 +1,42 @@
+use std::sync::{
+    Arc,
+    atomic::{AtomicBool, Ordering},
+};
+
+/// Exit code after stopping on Ctrl-C or SIGTERM, like a shell reports
+/// a process killed by SIGINT.
+pub const CANCELLED_EXIT_CODE: u8 = 130;
+
+/// Set once the user asks to stop, and checked by workers every batch.
+#[derive(Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Cancels on Ctrl-C or SIGTERM. A second signal exits right away,
+    /// in case stopping cleanly hangs.
+    pub fn from_signals() -> Self {
+        let token = Self::default();
+
+        let handler_token = token.clone();
+        let result = ctrlc::set_handler(move || {
+            if handler_token.is_cancelled() {
+                std::process::exit(CANCELLED_EXIT_CODE.into());
+            }
+
+            handler_token.cancel();
+        });
+        if let Err(error) = result {
+            eprintln!("Could not handle Ctrl-C: {error}");
+        }
+
+        token
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
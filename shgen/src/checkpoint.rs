@@ -0,0 +1,181 @@
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use ed25519_dalek::SECRET_KEY_LENGTH;
+use shgen_config_native::{
+    Config,
+    checkpoint::{Checkpoint, Seed},
+};
+use shgen_rand::Rng;
+
+use crate::{display, generate_keys::KEYS_PER_BATCH};
+
+/// Per-thread key streams of a deterministic search, shared with workers.
+pub struct Streams {
+    seed: Seed,
+    /// Keys every worker fully checked, with all of their matches sent.
+    checked: Box<[AtomicU64]>,
+}
+
+impl Streams {
+    /// Secret keys for `thread_id`, continuing after the keys it checked.
+    pub fn key_rng(&self, thread_id: usize) -> Rng {
+        let checked = self.checked[thread_id].load(Ordering::Relaxed);
+        Rng::from_seed_stream(
+            self.seed.0,
+            thread_id as u64,
+            checked * SECRET_KEY_LENGTH as u64,
+        )
+    }
+
+    /// Position of the next batch of `thread_id` in its stream, in keys.
+    pub fn next_batch(&self, thread_id: usize) -> u64 {
+        self.checked[thread_id].load(Ordering::Relaxed)
+    }
+
+    /// Marks the batch as checked, once all of its matches were sent.
+    pub fn complete_batch(&self, thread_id: usize) {
+        self.checked[thread_id].fetch_add(KEYS_PER_BATCH as u64, Ordering::Release);
+    }
+
+    /// Keys every worker fully checked.
+    pub fn checked(&self) -> Vec<u64> {
+        self.checked
+            .iter()
+            .map(|checked| checked.load(Ordering::Acquire))
+            .collect()
+    }
+}
+
+/// Writes checkpoints from the thread saving matches.
+pub struct Checkpoints {
+    path: PathBuf,
+    interval: Duration,
+    next_save: Instant,
+    search_hash: String,
+    resumed_matches: u64,
+    /// Thread and batch position of every match saved by this run.
+    saved: Vec<(usize, u64)>,
+}
+
+impl Checkpoints {
+    pub fn next_save(&self) -> Instant {
+        self.next_save
+    }
+
+    pub fn resumed_matches(&self) -> u64 {
+        self.resumed_matches
+    }
+
+    pub fn record_saved(&mut self, thread_id: usize, batch: u64) {
+        self.saved.push((thread_id, batch));
+    }
+
+    /// Saves progress up to the batches workers completed.
+    ///
+    /// `unsaved` lists the thread and batch position of matches that were
+    /// found but not saved, so that a resumed search finds them again. It is
+    /// only consumed after the workers' progress was read.
+    pub fn save(&mut self, streams: &Streams, unsaved: impl IntoIterator<Item = (usize, u64)>) {
+        let mut attempts = streams.checked();
+        for (thread_id, batch) in unsaved {
+            attempts[thread_id] = attempts[thread_id].min(batch);
+        }
+
+        // Matches in batches after the checkpoint will be found again.
+        let matches = self.resumed_matches
+            + self
+                .saved
+                .iter()
+                .filter(|&&(thread_id, batch)| batch < attempts[thread_id])
+                .count() as u64;
+
+        let checkpoint = Checkpoint {
+            seed: streams.seed,
+            search_hash: self.search_hash.clone(),
+            attempts,
+            matches,
+        };
+        if let Err(error) = checkpoint.save(&self.path) {
+            eprintln!("{error}");
+        }
+
+        self.next_save = Instant::now() + self.interval;
+    }
+}
+
+/// Starts a deterministic search if checkpoints are configured, resuming the
+/// saved one with `resume`. Resuming uses the checkpoint's number of threads.
+pub fn start(config: &mut Config, resume: bool) -> Result<Option<(Streams, Checkpoints)>, String> {
+    let Some(checkpoint_config) = &config.runtime.checkpoint else {
+        return if resume {
+            Err(String::from(
+                "Nothing to resume: `runtime.checkpoint` is not configured",
+            ))
+        } else {
+            Ok(None)
+        };
+    };
+
+    let checkpoint = if resume {
+        let checkpoint = Checkpoint::load(&checkpoint_config.path).map_err(|e| e.to_string())?;
+        if checkpoint.attempts.is_empty() || checkpoint.attempts.len() > 192 {
+            return Err(format!(
+                "Checkpoint {} must have between 1 and 192 threads",
+                checkpoint_config.path.display()
+            ));
+        }
+        if checkpoint.search_hash != config.search_hash {
+            return Err(format!(
+                "Checkpoint {} was saved for different keywords or search settings",
+                checkpoint_config.path.display()
+            ));
+        }
+
+        println!(
+            "Resuming after {} keys and {} matches",
+            display::thousands(checkpoint.attempts.iter().sum()),
+            checkpoint.matches
+        );
+        checkpoint
+    } else {
+        if checkpoint_config.path.exists() {
+            return Err(format!(
+                "Checkpoint {} already exists, use --resume to continue it or delete it to start over",
+                checkpoint_config.path.display()
+            ));
+        }
+
+        Checkpoint {
+            seed: checkpoint_config
+                .seed
+                .unwrap_or_else(|| Seed(rand::random())),
+            search_hash: config.search_hash.clone(),
+            attempts: vec![0; config.runtime.threads],
+            matches: 0,
+        }
+    };
+
+    let streams = Streams {
+        seed: checkpoint.seed,
+        checked: checkpoint
+            .attempts
+            .iter()
+            .map(|&attempts| AtomicU64::new(attempts))
+            .collect(),
+    };
+    let checkpoints = Checkpoints {
+        path: checkpoint_config.path.clone(),
+        interval: checkpoint_config.interval,
+        next_save: Instant::now() + checkpoint_config.interval,
+        search_hash: checkpoint.search_hash,
+        resumed_matches: checkpoint.matches,
+        saved: Vec::new(),
+    };
+
+    config.runtime.threads = checkpoint.attempts.len();
+    Ok(Some((streams, checkpoints)))
+}
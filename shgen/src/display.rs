@@ -0,0 +1,49 @@
+use std::time::Duration;
+
+pub fn thousands(value: u64) -> String {
+    let digits = value.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+
+    formatted
+}
+
+pub fn count(value: f64) -> String {
+    if !value.is_finite() {
+        String::from("infinite")
+    } else if value >= 1e15 {
+        format!("{value:.2e}")
+    } else {
+        thousands(value.ceil() as u64)
+    }
+}
+
+pub fn duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    let (days, hours) = (seconds / 86_400, seconds % 86_400 / 3600);
+    let (minutes, seconds) = (seconds % 3600 / 60, seconds % 60);
+
+    match days {
+        0 => format!("{hours:02}:{minutes:02}:{seconds:02}"),
+        1..365 => format!("{days}d {hours:02}:{minutes:02}:{seconds:02}"),
+        _ => format!("{}y {}d", days / 365, days % 365),
+    }
+}
+
+pub fn duration_secs(seconds: f64) -> String {
+    const SECONDS_PER_YEAR: f64 = 365.0 * 86_400.0;
+
+    if !seconds.is_finite() {
+        String::from("never")
+    } else if seconds >= 10_000.0 * SECONDS_PER_YEAR {
+        format!("{:.2e} years", seconds / SECONDS_PER_YEAR)
+    } else {
+        duration(Duration::from_secs_f64(seconds))
+    }
+}
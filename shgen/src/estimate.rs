@@ -0,0 +1,83 @@
+use shgen_config_native::Config;
+use shgen_key_utils::{estimate, matcher::Matcher};
+
+use crate::{benchmark, display};
+
+pub fn estimate(config: Config) {
+    let Some(probability) = estimate::match_probability(&config.shared) else {
+        println!("Regular expression keywords can't be estimated.");
+        return;
+    };
+
+    println!(
+        "{:<24}  {:<18}  {:>14}",
+        "keyword", "field", "expected keys"
+    );
+    let terms = match &config.shared.search.expression {
+        Some(expression) => expression.terms(),
+        None => config
+            .shared
+            .field_searches()
+            .flat_map(|search| {
+                search
+                    .keywords
+                    .iter()
+                    .map(move |keyword| (keyword, search.field))
+            })
+            .collect(),
+    };
+
+    for (keyword, field) in terms {
+        let probability = estimate::keyword_probability(
+            keyword,
+            field,
+            config.shared.search.matching.case_sensitive,
+        );
+
+        println!(
+            "{keyword:<24}  {field:<18}  {:>14}",
+            display::count(estimate::expected_attempts(probability))
+        );
+    }
+
+    let expected_attempts = estimate::expected_attempts(probability);
+
+    println!();
+    println!(
+        "Expected keys until a match: {}",
+        display::count(expected_attempts)
+    );
+
+    if probability == 0.0 {
+        println!("The configured keywords can never match.");
+        return;
+    }
+
+    println!(
+        "Measuring key rate with {} threads for {}...",
+        config.runtime.threads,
+        display::duration(config.benchmark.duration)
+    );
+
+    let matcher = Matcher::new(&config.shared);
+    let keys_per_second =
+        benchmark::measure_worker_rate(config.runtime.threads, config.benchmark.duration, &matcher);
+
+    println!("At {} keys/s:", display::thousands(keys_per_second as u64));
+    for (label, attempts) in [
+        (
+            "50% chance within",
+            estimate::attempts_for_chance(probability, 0.5),
+        ),
+        ("expected", expected_attempts),
+        (
+            "99% chance within",
+            estimate::attempts_for_chance(probability, 0.99),
+        ),
+    ] {
+        println!(
+            "  {label:<18} {}",
+            display::duration_secs(attempts / keys_per_second)
+        );
+    }
+}
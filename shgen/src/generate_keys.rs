@@ -1,13 +1,227 @@
-use std::thread;
+use std::{
+    collections::VecDeque,
+    process::ExitCode,
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::Instant,
+};
 
-use ed25519_dalek::{SECRET_KEY_LENGTH, SigningKey};
+use ed25519_dalek::{PUBLIC_KEY_LENGTH, SECRET_KEY_LENGTH};
 use rand::RngCore as _;
-use shgen_config_native::Config;
-use shgen_key_utils::{matcher::Matcher, openssh::format::Formatter};
+use shgen_config_core::search::scoring;
+use shgen_config_native::{Config, output::SaveError, runtime::MaxMatches};
+use shgen_key_utils::{
+    derive, estimate,
+    matcher::{KeywordMatch, Matcher, Scratch},
+    openssh::{Fingerprint, format::Formatter},
+    simd::SimdLevel,
+};
 use shgen_rand::Rng;
 use shgen_types::{OpenSSHPrivateKey, OpenSSHPublicKey};
 
-pub fn generate(config: Config) {
+use crate::{
+    best_keys,
+    cancel::{CANCELLED_EXIT_CODE, CancellationToken},
+    checkpoint::{self, Checkpoints, Streams},
+    display,
+    output::{Comment, Discovery, Output},
+    progress::{self, Progress},
+};
+
+pub const KEYS_PER_BATCH: usize = 8 * 32;
+
+/// A matching key pair, with where the worker found it.
+struct Match {
+    thread_id: usize,
+    /// Position of the batch in the worker's stream, in keys.
+    batch: u64,
+    key: FoundKey,
+}
+
+/// A key pair handed out by a [`Worker`], formatted for saving.
+pub struct FoundKey {
+    pub public_key: OpenSSHPublicKey,
+    pub private_key: OpenSSHPrivateKey,
+    /// Where each keyword is found in the key.
+    pub matches: Vec<KeywordMatch>,
+    /// Every fingerprint, indexed by [`Fingerprint`].
+    fingerprints: [String; 4],
+}
+
+impl FoundKey {
+    /// The distinct keywords found in the key.
+    pub fn keywords(&self) -> Vec<&str> {
+        keywords(&self.matches)
+    }
+
+    /// The fingerprint as `ssh-keygen -l` shows it after `SHA256:` and the
+    /// like.
+    pub fn fingerprint(&self, fingerprint: Fingerprint) -> &str {
+        &self.fingerprints[fingerprint as usize]
+    }
+}
+
+pub fn generate(mut config: Config, resume: bool) -> ExitCode {
+    let deterministic = match checkpoint::start(&mut config, resume) {
+        Ok(deterministic) => deterministic,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let output = match Output::new(&config) {
+        Ok(output) => output,
+        Err(error) => {
+            eprintln!("{error}");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Some(scoring) = &config.shared.search.scoring {
+        return best_keys::generate_best(&config, &output, scoring);
+    }
+
+    let match_probability = estimate::match_probability(&config.shared);
+    if match_probability == Some(0.0) {
+        eprintln!("The configured keywords can never match.");
+        return ExitCode::FAILURE;
+    }
+
+    let (streams, mut checkpoints) = deterministic.unzip();
+
+    let first_match = checkpoints.as_ref().map_or(0, Checkpoints::resumed_matches) + 1;
+    if let Err(error) = output.check_available(first_match) {
+        eprintln!("{error}");
+        return ExitCode::FAILURE;
+    }
+
+    let _keep_awake = keep_awake(&config);
+    eprintln!("Deriving keys with {} instructions", SimdLevel::detected());
+
+    let expected_attempts = match_probability.map(estimate::expected_attempts);
+    let matcher = Matcher::new(&config.shared);
+    let mut progress = Progress::new(
+        config.runtime.threads,
+        config.runtime.max_attempts,
+        CancellationToken::from_signals(),
+    );
+    if let Some(streams) = &streams {
+        progress = progress.resumed(&streams.checked());
+    }
+    let deadline = config
+        .runtime
+        .timeout
+        .map(|timeout| Instant::now() + timeout);
+
+    let (match_sender, match_receiver) = mpsc::channel();
+
+    let saved = thread::scope(|scope| {
+        for thread_id in 0..config.runtime.threads {
+            let matcher = &matcher;
+            let comment = output.comment();
+            let progress = &progress;
+            let streams = streams.as_ref();
+            let match_sender = match_sender.clone();
+
+            thread::Builder::new()
+                .name(format!("shgen-worker-{thread_id}"))
+                .spawn_scoped(scope, move || {
+                    worker(
+                        thread_id,
+                        matcher,
+                        comment,
+                        progress,
+                        streams,
+                        &match_sender,
+                    );
+                })
+                .expect("failed to spawn worker thread");
+        }
+
+        // Workers exiting at the attempt budget or on cancellation then end
+        // `save_matches`.
+        drop(match_sender);
+
+        let reporter_thread = thread::Builder::new()
+            .name(String::from("shgen-progress"))
+            .spawn_scoped(scope, || {
+                progress::report(
+                    &progress,
+                    config.runtime.progress_interval,
+                    expected_attempts,
+                );
+            })
+            .expect("failed to spawn progress thread");
+
+        let checkpoints = streams.as_ref().zip(checkpoints.as_mut());
+        let saved = save_matches(
+            &config,
+            &output,
+            &progress,
+            &match_receiver,
+            deadline,
+            checkpoints,
+        );
+
+        progress.finish();
+        reporter_thread.thread().unpark();
+        saved
+    });
+
+    let (matches, failed) = match saved {
+        Ok(matches) => (matches, None),
+        Err(failed) => (0, Some(failed)),
+    };
+
+    if let Some((streams, checkpoints)) = streams.as_ref().zip(checkpoints.as_mut()) {
+        // A match that failed to save is found again when resuming.
+        let unsaved = failed.iter().map(|failed| failed.position).chain(
+            match_receiver
+                .try_iter()
+                .map(|found| (found.thread_id, found.batch)),
+        );
+        checkpoints.save(streams, unsaved);
+    }
+
+    if let Some(failed) = failed {
+        progress::clear_line();
+        eprintln!("{}", failed.error);
+        return ExitCode::FAILURE;
+    }
+
+    if progress.is_cancelled() {
+        println!(
+            "Cancelled after {} keys in {}, with {matches} matches saved",
+            display::thousands(progress.attempts()),
+            display::duration(progress.elapsed())
+        );
+        return ExitCode::from(CANCELLED_EXIT_CODE);
+    }
+
+    // Only reachable without a match through the timeout or attempt budget.
+    if matches == 0 {
+        println!(
+            "No match found after {} keys in {}",
+            display::thousands(progress.attempts()),
+            display::duration(progress.elapsed())
+        );
+        return ExitCode::FAILURE;
+    }
+
+    if config.runtime.max_matches != MaxMatches::Count(1) {
+        println!(
+            "Found {matches} matches after {} keys in {}",
+            display::thousands(progress.attempts()),
+            display::duration(progress.elapsed())
+        );
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Keeps the system awake while the returned guard lives, if enabled.
+pub fn keep_awake(config: &Config) -> Option<shgen_keep_awake::KeepAwake> {
     let mut keep_awake = if config.runtime.keep_awake {
         match shgen_keep_awake::KeepAwake::new("shgen is generating keys") {
             Ok(guard) => Some(guard),
@@ -26,55 +240,268 @@ pub fn generate(config: Config) {
         eprintln!("Failed to prevent system sleep: {error}");
     }
 
-    let matcher = Matcher::new(config.shared.keywords, config.shared.search);
-    let found_key = thread::scope(|scope| {
-        let mut worker_threads = Vec::with_capacity(config.runtime.threads);
+    keep_awake
+}
 
-        for thread_id in 0..config.runtime.threads {
-            let matcher = &matcher;
+/// A match that couldn't be saved, which stops the search.
+struct SaveFailed {
+    error: SaveError,
+    /// Thread and batch of the match, for the checkpoint.
+    position: (usize, u64),
+}
+
+/// Saves matches as workers find them, until there are enough of them or
+/// the deadline passes, and saves checkpoints in between. Returns the number
+/// of saved matches, including those of a resumed search, or the first match
+/// that couldn't be saved.
+fn save_matches(
+    config: &Config,
+    output: &Output<'_>,
+    progress: &Progress,
+    match_receiver: &mpsc::Receiver<Match>,
+    deadline: Option<Instant>,
+    mut checkpoints: Option<(&Streams, &mut Checkpoints)>,
+) -> Result<u64, SaveFailed> {
+    let max_matches = config.runtime.max_matches;
+    let mut matches = checkpoints
+        .as_ref()
+        .map_or(0, |(_, checkpoints)| checkpoints.resumed_matches());
+    let mut pending = VecDeque::new();
+
+    while !max_matches.is_reached(matches) {
+        let next_checkpoint = checkpoints
+            .as_ref()
+            .map(|(_, checkpoints)| checkpoints.next_save());
+        let wake_up = deadline.into_iter().chain(next_checkpoint).min();
 
-            worker_threads.push(
-                thread::Builder::new()
-                    .name(format!("shgen-worker-{thread_id}"))
-                    .spawn_scoped(scope, move || worker(matcher))
-                    .expect("failed to spawn worker thread"),
+        let found = if let Some(found) = pending.pop_front() {
+            found
+        } else if let Some(wake_up) = wake_up {
+            match match_receiver.recv_timeout(wake_up.saturating_duration_since(Instant::now())) {
+                Ok(found) => found,
+                Err(RecvTimeoutError::Timeout) => {
+                    if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                        break;
+                    }
+
+                    // Matches still in the channel are saved right after.
+                    if let Some((streams, checkpoints)) = &mut checkpoints {
+                        let unsaved = match_receiver.try_iter().map(|found| {
+                            let position = (found.thread_id, found.batch);
+                            pending.push_back(found);
+                            position
+                        });
+                        checkpoints.save(streams, unsaved);
+                    }
+                    continue;
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        } else {
+            match match_receiver.recv() {
+                Ok(found) => found,
+                Err(_) => break,
+            }
+        };
+
+        matches += 1;
+
+        progress::clear_line();
+        if max_matches == MaxMatches::Count(1) {
+            println!(
+                "Found a match after {} keys in {}",
+                display::thousands(progress.attempts()),
+                display::duration(progress.elapsed())
+            );
+        } else {
+            println!(
+                "Found match {matches} after {} keys in {}",
+                display::thousands(progress.attempts()),
+                display::duration(progress.elapsed())
             );
         }
 
-        for thread in worker_threads {
-            if let Ok(Some(key_pair)) = thread.join() {
-                return Some(key_pair);
-            }
+        let discovery = Discovery {
+            thread_id: found.thread_id,
+            attempts: progress.attempts(),
+            elapsed: progress.elapsed(),
+            score: None,
+        };
+        if let Err(error) = output.save_keys(matches, &found.key, &discovery) {
+            return Err(SaveFailed {
+                error,
+                position: (found.thread_id, found.batch),
+            });
+        }
+        if let Some((_, checkpoints)) = &mut checkpoints {
+            checkpoints.record_saved(found.thread_id, found.batch);
         }
+    }
 
-        None
-    });
+    Ok(matches)
+}
 
-    if let Some((public_key, private_key)) = found_key {
-        config.output.save_keys(&public_key, &private_key);
+fn worker(
+    thread_id: usize,
+    matcher: &Matcher,
+    comment: &Comment,
+    progress: &Progress,
+    streams: Option<&Streams>,
+    match_sender: &mpsc::Sender<Match>,
+) {
+    let (worker, mut batch) = match streams {
+        Some(streams) => (
+            Worker::with_key_rng(matcher, streams.key_rng(thread_id)),
+            streams.next_batch(thread_id),
+        ),
+        None => (Worker::new(matcher), 0),
+    };
+    let mut worker = worker.with_comment(comment);
+
+    while !progress.is_finished() {
+        let mut counted = 0;
+        worker.search_batch(|checked, key| {
+            // Count the keys up to the match first, so it's reported accurately.
+            progress.add_attempts(thread_id, (checked - counted) as u64);
+            counted = checked;
+
+            // The receiver is only gone once enough matches were saved.
+            let _ = match_sender.send(Match {
+                thread_id,
+                batch,
+                key,
+            });
+        });
+        progress.add_attempts(thread_id, (KEYS_PER_BATCH - counted) as u64);
+
+        if let Some(streams) = streams {
+            streams.complete_batch(thread_id);
+        }
+        batch += KEYS_PER_BATCH as u64;
     }
 }
 
-fn worker(matcher: &Matcher) -> Option<(OpenSSHPublicKey, OpenSSHPrivateKey)> {
-    const BATCH_SIZE: usize = 8 * (SECRET_KEY_LENGTH * 32);
+pub struct Worker<'a> {
+    matcher: &'a Matcher,
+    /// Comment of the keys handed out, if any.
+    comment: Option<&'a Comment>,
+    /// Source of secret keys, the only randomness a key pair takes, so that
+    /// its position only depends on the number of keys checked.
+    key_rng: Rng,
+    formatter: Formatter,
+    scratch: Scratch,
+    secret_keys_batch: [u8; KEYS_PER_BATCH * SECRET_KEY_LENGTH],
+    public_keys_batch: [[u8; PUBLIC_KEY_LENGTH]; KEYS_PER_BATCH],
+}
+
+impl<'a> Worker<'a> {
+    pub fn new(matcher: &'a Matcher) -> Self {
+        Self::with_key_rng(matcher, Rng::from_best_available())
+    }
 
-    let mut rng = Rng::from_best_available();
-    let mut formatter = Formatter::empty();
+    /// Derives secret keys from `key_rng`, such as a seeded stream.
+    pub fn with_key_rng(matcher: &'a Matcher, key_rng: Rng) -> Self {
+        Self {
+            matcher,
+            comment: None,
+            key_rng,
+            formatter: Formatter::empty(),
+            scratch: matcher.scratch(),
+            secret_keys_batch: [0u8; KEYS_PER_BATCH * SECRET_KEY_LENGTH],
+            public_keys_batch: [[0u8; PUBLIC_KEY_LENGTH]; KEYS_PER_BATCH],
+        }
+    }
 
-    let mut secret_keys_batch = [0u8; BATCH_SIZE];
-    loop {
-        rng.fill_bytes(&mut secret_keys_batch);
+    /// Adds `comment` to the keys handed out.
+    pub const fn with_comment(mut self, comment: &'a Comment) -> Self {
+        self.comment = Some(comment);
+        self
+    }
+
+    /// Draws the next [`KEYS_PER_BATCH`] secret keys and derives their public
+    /// keys all at once.
+    fn next_batch(&mut self) {
+        self.key_rng.fill_bytes(&mut self.secret_keys_batch);
 
-        let (secret_keys_chunks, _) = secret_keys_batch.as_chunks::<SECRET_KEY_LENGTH>();
-        for secret_key in secret_keys_chunks {
-            let signing_key = SigningKey::from_bytes(secret_key);
-            formatter.update_keys(signing_key);
+        let (secret_keys_chunks, _) = self.secret_keys_batch.as_chunks::<SECRET_KEY_LENGTH>();
+        derive::public_keys(secret_keys_chunks, &mut self.public_keys_batch);
+    }
+
+    /// Scores the next [`KEYS_PER_BATCH`] keys, calling `on_better` for every
+    /// key scoring above `min_score`, which `on_better` returns the new value
+    /// of.
+    pub fn score_batch(
+        &mut self,
+        scoring: &scoring::Config,
+        mut min_score: f64,
+        mut on_better: impl FnMut(f64, FoundKey) -> f64,
+    ) {
+        self.next_batch();
+
+        let (secret_keys_chunks, _) = self.secret_keys_batch.as_chunks::<SECRET_KEY_LENGTH>();
+        for (secret_key, public_key) in secret_keys_chunks.iter().zip(&self.public_keys_batch) {
+            self.formatter.update_key_pair(secret_key, public_key);
+
+            let score = self
+                .matcher
+                .score(scoring, &mut self.formatter, &mut self.scratch);
+            if score > min_score {
+                let key = found_key(self.matcher, self.comment, &mut self.formatter);
+                min_score = on_better(score, key);
+            }
+        }
+    }
 
-            if let Some((public_key, private_key)) =
-                matcher.search_matches(&mut formatter, &mut rng)
+    /// Checks the next [`KEYS_PER_BATCH`] keys, calling `on_match` for every
+    /// match with the number of keys checked so far in the batch.
+    pub fn search_batch(&mut self, mut on_match: impl FnMut(usize, FoundKey)) {
+        self.next_batch();
+
+        let (secret_keys_chunks, _) = self.secret_keys_batch.as_chunks::<SECRET_KEY_LENGTH>();
+        let key_pairs = secret_keys_chunks.iter().zip(&self.public_keys_batch);
+        for (index, (secret_key, public_key)) in key_pairs.enumerate() {
+            self.formatter.update_key_pair(secret_key, public_key);
+
+            if self
+                .matcher
+                .is_match(&mut self.formatter, &mut self.scratch)
             {
-                return Some((public_key, private_key));
+                let key = found_key(self.matcher, self.comment, &mut self.formatter);
+                on_match(index + 1, key);
             }
         }
     }
 }
+
+/// Formats the current key pair of `formatter` with its comment.
+fn found_key(matcher: &Matcher, comment: Option<&Comment>, formatter: &mut Formatter) -> FoundKey {
+    let matches = matcher.keyword_matches(formatter);
+    if let Some(comment) = comment {
+        formatter.set_comment(&comment.render(&keywords(&matches)));
+    }
+
+    FoundKey {
+        public_key: formatter.format_public_key(),
+        private_key: formatter.format_private_key(),
+        fingerprints: [
+            Fingerprint::Sha1,
+            Fingerprint::Sha256,
+            Fingerprint::Sha384,
+            Fingerprint::Sha512,
+        ]
+        .map(|fingerprint| formatter.format_fingerprint(&fingerprint)),
+        matches,
+    }
+}
+
+/// The distinct keywords of `matches`, in order.
+fn keywords(matches: &[KeywordMatch]) -> Vec<&str> {
+    let mut keywords: Vec<&str> = Vec::new();
+    for found in matches {
+        if !keywords.contains(&found.keyword.as_str()) {
+            keywords.push(&found.keyword);
+        }
+    }
+
+    keywords
+}
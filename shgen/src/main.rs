@@ -1,21 +1,39 @@
 #![allow(clippy::cast_precision_loss)]
 #![allow(clippy::cast_possible_truncation)]
 
+mod benchmark;
+mod best_keys;
+mod cancel;
+mod checkpoint;
+mod display;
+mod estimate;
 mod generate_keys;
+mod output;
+mod progress;
 
+use std::process::ExitCode;
+
+use benchmark::benchmark;
+use estimate::estimate;
 use generate_keys::generate;
 use shgen_cli::{Cli, Command};
-use shgen_config_native::Config;
+use shgen_config_native::{Config, output::Existing};
 
 #[global_allocator]
 static ALLOCATOR: mimalloc::MiMalloc = mimalloc::MiMalloc;
 
-fn main() {
+fn main() -> ExitCode {
     let cli = Cli::try_parse().unwrap();
-    let config = Config::load(cli.config).unwrap();
+    let mut config = Config::load(cli.config, cli.expression).unwrap();
+    if cli.force {
+        config.output.existing = Existing::Overwrite;
+    }
 
     match cli.command {
-        Command::Benchmark => unimplemented!("benchmark command is not yet implemented"),
-        Command::Generate => generate(config),
+        Command::Benchmark => benchmark(config),
+        Command::Generate => return generate(config, cli.resume),
+        Command::Estimate => estimate(config),
     }
+
+    ExitCode::SUCCESS
 }
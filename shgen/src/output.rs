@@ -0,0 +1,256 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime},
+};
+
+use shgen_config_native::{
+    Config,
+    output::{self, SaveError},
+    report::{self, Record, Report},
+    runtime::MaxMatches,
+};
+use shgen_key_utils::openssh::{
+    Fingerprint,
+    encrypt::{self, Cipher},
+};
+use zeroize::Zeroizing;
+
+use crate::generate_keys::FoundKey;
+
+/// Placeholders of `output.filename` that depend on the key.
+const KEY_PLACEHOLDERS: [&str; 3] = ["{keyword}", "{fingerprint}", "{fingerprint8}"];
+
+/// Saves found keys as `output` configures, encrypting private keys
+/// before they are written.
+pub struct Output<'a> {
+    config: &'a output::Config,
+    /// `output.filename`, or its default for the number of saved keys.
+    filename: String,
+    /// Names saved so far, which later keys of the same search never take.
+    saved: Mutex<HashSet<String>>,
+    comment: Comment,
+    encryption: Option<Encryption<'a>>,
+    report: Option<Report>,
+    search_hash: &'a str,
+}
+
+/// Where and when a key was found, for the report.
+pub struct Discovery {
+    pub thread_id: usize,
+    /// Keys checked by all threads so far.
+    pub attempts: u64,
+    pub elapsed: Duration,
+    pub score: Option<f64>,
+}
+
+/// `output.comment`, with every placeholder but `{keyword}` filled in once.
+pub struct Comment {
+    text: String,
+}
+
+struct Encryption<'a> {
+    config: &'a output::Encryption,
+    passphrase: Zeroizing<String>,
+}
+
+impl<'a> Output<'a> {
+    /// Reads the passphrase if keys are encrypted and opens the report, so
+    /// that a missing passphrase or an unwritable report stops the search
+    /// before it starts.
+    pub fn new(config: &'a Config) -> Result<Self, String> {
+        let encryption = config
+            .output
+            .encryption
+            .as_ref()
+            .map(|encryption| {
+                Ok::<_, String>(Encryption {
+                    config: encryption,
+                    passphrase: encryption.passphrase.read().map_err(|e| e.to_string())?,
+                })
+            })
+            .transpose()?;
+        let report = config
+            .output
+            .report
+            .as_deref()
+            .map(Report::open)
+            .transpose()
+            .map_err(|e| e.to_string())?;
+
+        let saves_several = match &config.shared.search.scoring {
+            Some(scoring) => scoring.keep > 1,
+            None => config.runtime.max_matches != MaxMatches::Count(1),
+        };
+        let filename = match &config.output.filename {
+            Some(filename) => filename.clone(),
+            None if saves_several => String::from("id_ed25519_{n}"),
+            None => String::from("id_ed25519"),
+        };
+
+        Ok(Self {
+            config: &config.output,
+            filename,
+            saved: Mutex::new(HashSet::new()),
+            comment: Comment::new(&config.output.comment),
+            encryption,
+            report,
+            search_hash: &config.search_hash,
+        })
+    }
+
+    pub const fn comment(&self) -> &Comment {
+        &self.comment
+    }
+
+    /// Checks that key number `number` can be saved, if its name doesn't
+    /// depend on the key. See [`output::Config::check_available`].
+    pub fn check_available(&self, number: u64) -> Result<(), SaveError> {
+        if KEY_PLACEHOLDERS
+            .iter()
+            .any(|placeholder| self.filename.contains(placeholder))
+        {
+            return Ok(());
+        }
+
+        self.config
+            .check_available(&self.filename.replace("{n}", &number.to_string()))
+    }
+
+    /// Saves key number `number`, such as the rank of a scored key, and
+    /// reports it. A name already saved by this search gets a `-2`, `-3`
+    /// and so on suffix, and a name that renders empty is `id_ed25519`.
+    pub fn save_keys(
+        &self,
+        number: u64,
+        found: &FoundKey,
+        discovery: &Discovery,
+    ) -> Result<PathBuf, SaveError> {
+        let fingerprint = found.fingerprint(Fingerprint::Sha256);
+        let name = self
+            .filename
+            .replace("{n}", &number.to_string())
+            .replace("{keyword}", &file_safe(&found.keywords().join("-")))
+            .replace("{fingerprint8}", &file_safe(&fingerprint[..8]))
+            .replace("{fingerprint}", &file_safe(fingerprint));
+        // Placeholders alone can render to no name, or to a directory.
+        let name = match name.as_str() {
+            "" | "." | ".." => String::from("id_ed25519"),
+            _ => name,
+        };
+
+        let mut saved = self.saved.lock().expect("no thread panics while saving");
+        let name = (1..)
+            .map(|n| match n {
+                1 => name.clone(),
+                n => format!("{name}-{n}"),
+            })
+            .find(|candidate| !saved.contains(candidate))
+            .expect("some suffix is free");
+
+        let path = self.save_encrypted(&name, found)?;
+        saved.insert(
+            path.file_name()
+                .expect("key path has a file name")
+                .to_string_lossy()
+                .into_owned(),
+        );
+        drop(saved);
+
+        if let Some(report) = &self.report {
+            // The key is saved either way, so only tell about it.
+            if let Err(error) = report.append(&self.record(&path, found, discovery)) {
+                eprintln!("{error}");
+            }
+        }
+
+        Ok(path)
+    }
+
+    fn record(&self, path: &Path, found: &FoundKey, discovery: &Discovery) -> Record {
+        Record {
+            path: path.to_owned(),
+            public_key: String::from(&*found.public_key),
+            fingerprints: report::Fingerprints {
+                sha1: format!("SHA1:{}", found.fingerprint(Fingerprint::Sha1)),
+                sha256: format!("SHA256:{}", found.fingerprint(Fingerprint::Sha256)),
+                sha384: format!("SHA384:{}", found.fingerprint(Fingerprint::Sha384)),
+                sha512: format!("SHA512:{}", found.fingerprint(Fingerprint::Sha512)),
+            },
+            matches: found
+                .matches
+                .iter()
+                .map(|found| report::KeywordMatch {
+                    keyword: found.keyword.clone(),
+                    field: found.field.clone(),
+                    offsets: found.offsets.clone(),
+                })
+                .collect(),
+            score: discovery.score,
+            attempts: discovery.attempts,
+            elapsed: discovery.elapsed.as_secs_f64(),
+            thread_id: discovery.thread_id,
+            search_hash: self.search_hash.to_owned(),
+        }
+    }
+
+    fn save_encrypted(&self, name: &str, found: &FoundKey) -> Result<PathBuf, SaveError> {
+        let (public_key, private_key) = (&found.public_key, &found.private_key);
+        let Some(encryption) = &self.encryption else {
+            return self.config.save_keys(name, public_key, private_key);
+        };
+
+        let cipher = match encryption.config.cipher {
+            output::Cipher::Aes256Ctr => Cipher::Aes256Ctr,
+            output::Cipher::Aes256Gcm => Cipher::Aes256Gcm,
+        };
+        let private_key = encrypt::encrypt_private_key(
+            private_key,
+            cipher,
+            encryption.config.rounds,
+            &encryption.passphrase,
+            rand::random(),
+        );
+
+        self.config.save_keys(name, public_key, &private_key)
+    }
+}
+
+impl Comment {
+    fn new(template: &str) -> Self {
+        let mut text = template.to_owned();
+        if text.contains("{user}") {
+            text = text.replace("{user}", &whoami::username());
+        }
+        if text.contains("{host}") {
+            let host = whoami::fallible::hostname().unwrap_or_else(|_| String::from("localhost"));
+            text = text.replace("{host}", &host);
+        }
+        if text.contains("{date}") {
+            // `YYYY-MM-DD` in UTC.
+            let timestamp = humantime::format_rfc3339_seconds(SystemTime::now()).to_string();
+            text = text.replace("{date}", &timestamp[..10]);
+        }
+
+        Self { text }
+    }
+
+    /// The comment of a key in which `keywords` were found.
+    pub fn render(&self, keywords: &[&str]) -> String {
+        self.text.replace("{keyword}", &keywords.join(","))
+    }
+}
+
+/// `text` with only characters that are safe in a file name on every
+/// platform. Base64 maps to its URL-safe alphabet.
+fn file_safe(text: &str) -> String {
+    text.chars()
+        .map(|character| match character {
+            '+' => '-',
+            '/' => '_',
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '_' | '-' => character,
+            _ => '_',
+        })
+        .collect()
+}
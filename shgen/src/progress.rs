@@ -0,0 +1,163 @@
+use std::{
+    io::{IsTerminal as _, Write as _},
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{cancel::CancellationToken, display};
+
+#[repr(align(64))]
+struct PaddedCounter(AtomicU64);
+
+pub struct Progress {
+    counters: Box<[PaddedCounter]>,
+    finished: AtomicBool,
+    started: Instant,
+    /// Finishes once this many attempts were made.
+    max_attempts: Option<u64>,
+    /// Attempts made before resuming, left out of the rate.
+    resumed_attempts: u64,
+    /// Bits of the best score recorded so far, if scoring.
+    best_score: AtomicU64,
+    cancellation: CancellationToken,
+}
+
+impl Progress {
+    pub fn new(threads: usize, max_attempts: Option<u64>, cancellation: CancellationToken) -> Self {
+        Self {
+            counters: (0..threads)
+                .map(|_| PaddedCounter(AtomicU64::new(0)))
+                .collect(),
+            finished: AtomicBool::new(false),
+            started: Instant::now(),
+            max_attempts,
+            resumed_attempts: 0,
+            best_score: AtomicU64::new(f64::NEG_INFINITY.to_bits()),
+            cancellation,
+        }
+    }
+
+    /// Starts the counters from the attempts of a resumed search.
+    pub fn resumed(mut self, attempts: &[u64]) -> Self {
+        for (counter, &attempts) in self.counters.iter().zip(attempts) {
+            counter.0.store(attempts, Ordering::Relaxed);
+        }
+        self.resumed_attempts = attempts.iter().sum();
+        self
+    }
+
+    pub fn add_attempts(&self, thread_id: usize, attempts: u64) {
+        self.counters[thread_id]
+            .0
+            .fetch_add(attempts, Ordering::Relaxed);
+
+        if let Some(max_attempts) = self.max_attempts
+            && self.attempts() >= max_attempts
+        {
+            self.finish();
+        }
+    }
+
+    pub fn record_score(&self, score: f64) {
+        let _ = self
+            .best_score
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |best| {
+                (score > f64::from_bits(best)).then_some(score.to_bits())
+            });
+    }
+
+    /// The best score recorded so far, if any.
+    pub fn best_score(&self) -> Option<f64> {
+        let best_score = f64::from_bits(self.best_score.load(Ordering::Relaxed));
+        (best_score > f64::NEG_INFINITY).then_some(best_score)
+    }
+
+    pub fn attempts(&self) -> u64 {
+        self.counters
+            .iter()
+            .map(|counter| counter.0.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// Attempts made since starting or resuming.
+    pub fn new_attempts(&self) -> u64 {
+        self.attempts() - self.resumed_attempts
+    }
+
+    pub fn finish(&self) {
+        self.finished.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the search is over, either normally or because it was
+    /// cancelled.
+    pub fn is_finished(&self) -> bool {
+        self.finished.load(Ordering::Relaxed) || self.is_cancelled()
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+}
+
+/// Clears the progress line on a terminal, so that other output
+/// doesn't get mixed into it.
+pub fn clear_line() {
+    if std::io::stderr().is_terminal() {
+        eprint!("\r\x1b[2K");
+    }
+}
+
+/// Prints progress every `interval` until [`Progress::finish`] is called.
+///
+/// The reporting thread should be unparked after finishing, so that it
+/// doesn't wait for the rest of the interval.
+pub fn report(progress: &Progress, interval: Duration, expected_attempts: Option<f64>) {
+    let is_terminal = std::io::stderr().is_terminal();
+
+    while !progress.is_finished() {
+        thread::park_timeout(interval);
+
+        let elapsed = progress.elapsed();
+        let attempts = progress.attempts();
+        let keys_per_second = progress.new_attempts() as f64 / elapsed.as_secs_f64();
+
+        let mut line = format!(
+            "{} | {} keys | {} keys/s",
+            display::duration(elapsed),
+            display::thousands(attempts),
+            display::thousands(keys_per_second as u64),
+        );
+
+        if let Some(best_score) = progress.best_score() {
+            line.push_str(&format!(" | best score {best_score:.2}"));
+        }
+
+        if let Some(expected_attempts) = expected_attempts {
+            let remaining_attempts = (expected_attempts - attempts as f64).max(0.0);
+            let chance = 1.0 - (-(attempts as f64) / expected_attempts).exp();
+
+            line.push_str(&format!(
+                " | eta {} ({:.0}% chance so far)",
+                display::duration_secs(remaining_attempts / keys_per_second),
+                chance * 100.0,
+            ));
+        }
+
+        let mut stderr = std::io::stderr().lock();
+        if is_terminal {
+            let _ = write!(stderr, "\r\x1b[2K{line}");
+            let _ = stderr.flush();
+        } else {
+            let _ = writeln!(stderr, "{line}");
+        }
+    }
+
+    if is_terminal {
+        eprintln!();
+    }
+}
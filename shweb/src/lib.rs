@@ -3,41 +3,87 @@ use rand_chacha::{
     ChaCha8Rng,
     rand_core::{RngCore, SeedableRng},
 };
-use shgen_config_wasm::{Config, MatchingConfig, SearchConfig, SearchFields};
-use shgen_key_utils::{matcher::Matcher, openssh::format::Formatter};
+use shgen_config_wasm::{
+    Config, Keyword, KeywordSyntax, MatchingConfig, SearchConfig, SearchFields,
+};
+use shgen_key_utils::{
+    estimate,
+    matcher::{Matcher, Scratch},
+    openssh::format::Formatter,
+};
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen]
 pub struct Generator {
     matcher: Matcher,
     formatter: Formatter,
+    scratch: Scratch,
     rng: ChaCha8Rng,
+    match_probability: Option<f64>,
 }
 
 #[wasm_bindgen]
 impl Generator {
     #[wasm_bindgen(constructor)]
-    #[must_use]
     pub fn new(
-        keywords: Vec<String>,
+        keywords: Vec<Keyword>,
         fields: Vec<SearchFields>,
         all_keywords: bool,
         all_fields: bool,
-    ) -> Self {
-        let matching_config = MatchingConfig::new(all_keywords, all_fields);
+        syntax: KeywordSyntax,
+        case_sensitive: bool,
+    ) -> Result<Self, JsError> {
+        let matching_config = MatchingConfig::new(all_keywords, all_fields, syntax, case_sensitive);
         let search_config = SearchConfig::new(fields, matching_config);
-        let config = Config::new(keywords, search_config);
+        Self::from_config(Config::new(keywords, search_config))
+    }
 
-        let matcher = Matcher::new(config.keywords(), config.search().into());
+    /// Searches for a boolean expression such as `cat in public-key and not dog in sha256`.
+    #[wasm_bindgen(js_name = fromExpression)]
+    pub fn from_expression(
+        expression: &str,
+        syntax: KeywordSyntax,
+        case_sensitive: bool,
+    ) -> Result<Self, JsError> {
+        let matching_config = MatchingConfig::new(false, false, syntax, case_sensitive);
+        let search_config = SearchConfig::with_expression(expression, matching_config)?;
+        Self::from_config(Config::new(Vec::new(), search_config))
+    }
+
+    fn from_config(config: Config) -> Result<Self, JsError> {
+        config.validate()?;
+
+        let config: shgen_config_core::Config = config.into();
+
+        let match_probability = estimate::match_probability(&config);
+        if match_probability == Some(0.0) {
+            return Err(JsError::new("The configured keywords can never match"));
+        }
+
+        let matcher = Matcher::new(&config);
 
         let mut seed = [0u8; 32];
         getrandom::fill(&mut seed).unwrap();
 
-        Self {
+        Ok(Self {
+            scratch: matcher.scratch(),
             matcher,
             formatter: Formatter::empty(),
             rng: ChaCha8Rng::from_seed(seed),
-        }
+            match_probability,
+        })
+    }
+
+    #[wasm_bindgen(getter, js_name = matchProbability)]
+    #[must_use]
+    pub fn match_probability(&self) -> Option<f64> {
+        self.match_probability
+    }
+
+    #[wasm_bindgen(getter, js_name = expectedAttempts)]
+    #[must_use]
+    pub fn expected_attempts(&self) -> Option<f64> {
+        self.match_probability.map(estimate::expected_attempts)
     }
 
     #[wasm_bindgen(js_name = generateBatch)]
@@ -54,7 +100,7 @@ impl Generator {
 
             if let Some((public_key, private_key)) = self
                 .matcher
-                .search_matches(&mut self.formatter, &mut self.rng)
+                .search_matches(&mut self.formatter, &mut self.scratch)
             {
                 return js_sys::Array::of2(
                     &JsValue::from_str(&public_key),
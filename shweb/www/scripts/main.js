@@ -8,12 +8,20 @@ const $ = (selector) => document.querySelector(selector);
 
 const elements = {
   keywordsInput: $("#search-keywords"),
+  expressionInput: $("#search-expression"),
   workersCountInput: $("#workers-count"),
   fieldsSelect: $("#search-in-fields"),
   anyKeywordRadio: $("#any-keyword"),
   allKeywordsRadio: $("#all-keywords"),
   anyFieldRadio: $("#any-field"),
   allFieldsRadio: $("#all-fields"),
+  literalSyntaxRadio: $("#literal-syntax"),
+  regexSyntaxRadio: $("#regex-syntax"),
+  ignoreCaseRadio: $("#ignore-case"),
+  exactCaseRadio: $("#exact-case"),
+  anywhereAnchorRadio: $("#anywhere-anchor"),
+  startAnchorRadio: $("#start-anchor"),
+  endAnchorRadio: $("#end-anchor"),
   startButton: $('[data-action="start"]'),
   stopButton: $('[data-action="stop"]'),
   resetButton: $('[data-action="reset"]'),
@@ -21,6 +29,7 @@ const elements = {
   runningTime: $(".stat:nth-child(1) .stat-value"),
   keysGenerated: $(".stat:nth-child(2) .stat-value"),
   keysPerSec: $(".stat:nth-child(3) .stat-value"),
+  eta: $(".stat:nth-child(4) .stat-value"),
   publicKeyValue: $(".key-info:nth-child(1) .key-value code"),
   privateKeyValue: $(".key-info:nth-child(2) .key-value code"),
 };
@@ -31,6 +40,7 @@ class Shweb {
   #isGenerating = false;
   #startTime = 0;
   #totalKeysGenerated = 0;
+  #expectedAttempts;
 
   /**
    * @type {number | null}
@@ -65,12 +75,20 @@ class Shweb {
 
     const settingsInputs = [
       elements.keywordsInput,
+      elements.expressionInput,
       elements.workersCountInput,
       elements.fieldsSelect,
       elements.anyKeywordRadio,
       elements.allKeywordsRadio,
       elements.anyFieldRadio,
       elements.allFieldsRadio,
+      elements.literalSyntaxRadio,
+      elements.regexSyntaxRadio,
+      elements.ignoreCaseRadio,
+      elements.exactCaseRadio,
+      elements.anywhereAnchorRadio,
+      elements.startAnchorRadio,
+      elements.endAnchorRadio,
     ];
 
     for (const element of settingsInputs) {
@@ -95,50 +113,83 @@ class Shweb {
     )}`;
   }
 
+  #formatEta(remainingKeys, rate) {
+    if (Number.isNaN(remainingKeys)) return "unknown";
+    if (!rate || !Number.isFinite(remainingKeys)) return "never";
+
+    const seconds = remainingKeys / rate;
+    const years = seconds / (365 * 24 * 3600);
+
+    return years >= 1
+      ? `${Math.round(years).toLocaleString()} years`
+      : this.#formatTime(seconds);
+  }
+
   #updateStats() {
     if (!this.#isGenerating) return;
 
     const elapsed = (performance.now() - this.#startTime) / 1000;
     const rate = elapsed ? Math.round(this.#totalKeysGenerated / elapsed) : 0;
+    const remainingKeys = Math.max(
+      (this.#expectedAttempts ?? NaN) - this.#totalKeysGenerated,
+      0
+    );
 
     elements.runningTime.textContent = this.#formatTime(elapsed);
     elements.keysGenerated.textContent =
       this.#totalKeysGenerated.toLocaleString();
     elements.keysPerSec.textContent = rate.toLocaleString();
+    elements.eta.textContent = this.#formatEta(remainingKeys, rate);
   }
 
   #getConfig() {
+    const anchor = this.#getAnchor();
     const keywords = elements.keywordsInput.value
       .split(",")
       .map((keyword) => keyword.trim())
-      .filter(Boolean);
+      .filter(Boolean)
+      .map((keyword) => (anchor ? { keyword, anchor } : keyword));
 
     const fields = [...elements.fieldsSelect.selectedOptions].map(
       (option) => option.value
     );
 
+    const expression = elements.expressionInput.value.trim();
+
     return {
       keywords,
       search: {
         fields,
+        expression: expression || null,
         matching: {
           "all-keywords": elements.allKeywordsRadio.checked,
           "all-fields": elements.allFieldsRadio.checked,
+          syntax: elements.regexSyntaxRadio.checked ? "regex" : "literal",
+          "case-sensitive": elements.exactCaseRadio.checked,
         },
       },
     };
   }
 
-  #validateConfig({ keywords, search: { fields } }) {
+  #getAnchor() {
+    if (elements.startAnchorRadio.checked) return "start";
+    if (elements.endAnchorRadio.checked) return "end";
+    return null;
+  }
+
+  #validateConfig({ keywords, search: { fields, expression } }) {
+    if (expression) return;
     if (!keywords.length) throw new Error("Enter at least one keyword");
     if (!fields.length) throw new Error("Select at least one field");
   }
 
   #handleWorkerMessage = ({ data, target }) => {
-    const { type, data: payload, keysGenerated, error } = data;
+    const { type, data: payload, keysGenerated, expectedAttempts, error } =
+      data;
 
     switch (type) {
       case "init":
+        this.#expectedAttempts = expectedAttempts;
         target.postMessage({ type: "start" });
         break;
       case "progress":
@@ -258,6 +309,7 @@ class Shweb {
     this.stop();
 
     elements.keywordsInput.value = "";
+    elements.expressionInput.value = "";
     setWorkerCountToCpuCores();
 
     this.#totalKeysGenerated = 0;
@@ -265,11 +317,15 @@ class Shweb {
     elements.runningTime.textContent = "00:00:00";
     elements.keysGenerated.textContent = "0";
     elements.keysPerSec.textContent = "0";
+    elements.eta.textContent = "00:00:00";
     elements.publicKeyValue.textContent = elements.privateKeyValue.textContent =
       "...";
 
     elements.anyKeywordRadio.checked = elements.allFieldsRadio.checked = true;
     elements.allKeywordsRadio.checked = elements.anyFieldRadio.checked = false;
+    elements.literalSyntaxRadio.checked = true;
+    elements.ignoreCaseRadio.checked = true;
+    elements.anywhereAnchorRadio.checked = true;
 
     this.#setStatus("ready");
 
@@ -279,10 +335,14 @@ class Shweb {
   #saveSettings() {
     const settings = {
       keywords: elements.keywordsInput.value,
+      expression: elements.expressionInput.value,
       workersCount: elements.workersCountInput.value,
       fields: [...elements.fieldsSelect.selectedOptions].map((o) => o.value),
       keywordMatching: elements.allKeywordsRadio.checked ? "all" : "any",
       fieldMatching: elements.allFieldsRadio.checked ? "all" : "any",
+      keywordSyntax: elements.regexSyntaxRadio.checked ? "regex" : "literal",
+      letterCase: elements.exactCaseRadio.checked ? "exact" : "ignore",
+      keywordAnchor: this.#getAnchor() ?? "anywhere",
     };
 
     localStorage.setItem(STORAGE_KEY, JSON.stringify(settings));
@@ -293,6 +353,7 @@ class Shweb {
     if (!settings) return;
 
     elements.keywordsInput.value = settings.keywords ?? "";
+    elements.expressionInput.value = settings.expression ?? "";
     elements.workersCountInput.value =
       settings.workersCount ?? elements.workersCountInput.value;
 
@@ -309,6 +370,21 @@ class Shweb {
       ? elements.allFieldsRadio
       : elements.anyFieldRadio
     ).checked = true;
+
+    (settings.keywordSyntax === "regex"
+      ? elements.regexSyntaxRadio
+      : elements.literalSyntaxRadio
+    ).checked = true;
+
+    (settings.letterCase === "exact"
+      ? elements.exactCaseRadio
+      : elements.ignoreCaseRadio
+    ).checked = true;
+
+    ({
+      start: elements.startAnchorRadio,
+      end: elements.endAnchorRadio,
+    }[settings.keywordAnchor] ?? elements.anywhereAnchorRadio).checked = true;
   }
 }
 
@@ -1,4 +1,10 @@
-import init, { Generator, SearchFields } from "../shweb-wasm/shweb.js";
+import init, {
+  Anchor,
+  Generator,
+  Keyword,
+  KeywordSyntax,
+  SearchFields,
+} from "../shweb-wasm/shweb.js";
 
 // Keep it synced with shweb/src/lib.rs
 const BATCH_SIZE = (8 * 1024) / 32;
@@ -30,16 +36,51 @@ class SSHKeyWorker {
     };
     const fields = config.search.fields.map((field) => fieldMap[field]);
 
+    const syntax =
+      config.search.matching.syntax === "regex"
+        ? KeywordSyntax.Regex
+        : KeywordSyntax.Literal;
+
+    const anchorMap = {
+      start: () => Anchor.start(),
+      end: () => Anchor.end(),
+    };
+    const keywords = config.keywords.map((keyword) =>
+      typeof keyword === "string"
+        ? new Keyword(keyword, undefined, undefined)
+        : new Keyword(
+            keyword.keyword,
+            anchorMap[keyword.anchor]?.(),
+            keyword["case-sensitive"]
+          )
+    );
+
+    const caseSensitive = config.search.matching["case-sensitive"];
+    if (config.search.expression) {
+      this.#generator = Generator.fromExpression(
+        config.search.expression,
+        syntax,
+        caseSensitive
+      );
+      return { success: true };
+    }
+
     this.#generator = new Generator(
-      config.keywords,
+      keywords,
       fields,
       config.search.matching["all-keywords"],
-      config.search.matching["all-fields"]
+      config.search.matching["all-fields"],
+      syntax,
+      caseSensitive
     );
 
     return { success: true };
   }
 
+  get expectedAttempts() {
+    return this.#generator?.expectedAttempts;
+  }
+
   async start() {
     if (!this.#generator) throw new Error("Not initialised");
     if (this.#isRunning) throw new Error("Already running");
@@ -104,7 +145,11 @@ const worker = new SSHKeyWorker();
 const handlers = {
   async init({ config }) {
     await worker.initialise(config);
-    worker.post({ type: "init", batchSize: BATCH_SIZE });
+    worker.post({
+      type: "init",
+      batchSize: BATCH_SIZE,
+      expectedAttempts: worker.expectedAttempts,
+    });
   },
   start: () => worker.start(),
   stop: () => {